@@ -0,0 +1,95 @@
+// ============================================
+// CHUNK CULLING
+// One thread per chunk slot: frustum test against the chunk's bounds, then
+// append the slot to the visible list of its level of detail and bump that
+// level's indirect instance count.
+// ============================================
+
+// Set per pipeline from TerrainQuality
+override CHUNK_SIZE: u32 = 64u;  // Vertices per chunk edge
+override CHUNK_WORLD_SIZE: f32 = 256.0;  // World units per chunk
+
+struct CullParams {
+    planes: array<vec4f, 6>,  // Left, right, bottom, top, near, far
+    camera_pos: vec4f,        // w unused
+    lod_distances: vec4f,     // Distances in chunks at which chunks switch to the next level
+    skirt_depth: f32,         // Deepest LOD skirt, added below each chunk's minimum height
+    slot_count: u32,
+    lod_count: u32,
+    visible_stride: u32,      // Entries reserved per level in visible_chunks
+}
+
+// Must match ChunkInfo in terrain.wgsl
+struct ChunkInfo {
+    world_offset: vec2f,
+    height_offset: u32,  // First sample of the chunk in the shared height buffer
+    ready: u32,
+    min_height: f32,     // Written by reduce_height_bounds in terrain.wgsl
+    max_height: f32,
+    ao_offset: u32,      // First value of the chunk in the shared AO buffer
+    _padding: u32,
+}
+
+// Layout of a DrawIndexedIndirect argument block
+struct DrawArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: CullParams;
+@group(0) @binding(1) var<storage, read> chunks: array<ChunkInfo>;
+@group(0) @binding(2) var<storage, read_write> draws: array<DrawArgs>;
+@group(0) @binding(3) var<storage, read_write> visible_chunks: array<u32>;
+
+// Conservative AABB test: false only if the box is fully outside a plane
+fn in_frustum(min_corner: vec3f, max_corner: vec3f) -> bool {
+    for (var i = 0u; i < 6u; i++) {
+        let plane = params.planes[i];
+        // Corner of the box furthest along the plane normal
+        let p = select(min_corner, max_corner, plane.xyz >= vec3f(0.0));
+        if (dot(plane.xyz, p) + plane.w < 0.0) {
+            return false;
+        }
+    }
+    return true;
+}
+
+@compute @workgroup_size(64)
+fn cull_chunks(@builtin(global_invocation_id) id: vec3u) {
+    let slot = id.x;
+    if (slot >= params.slot_count) {
+        return;
+    }
+
+    let chunk = chunks[slot];
+    if (chunk.ready == 0u) {
+        return;
+    }
+
+    let min_corner = vec3f(chunk.world_offset.x, chunk.min_height - params.skirt_depth, chunk.world_offset.y);
+    let max_corner = vec3f(
+        chunk.world_offset.x + CHUNK_WORLD_SIZE,
+        chunk.max_height,
+        chunk.world_offset.y + CHUNK_WORLD_SIZE
+    );
+    if (!in_frustum(min_corner, max_corner)) {
+        return;
+    }
+
+    // Horizontal distance from the camera to the chunk centre, in chunks
+    let center = chunk.world_offset + vec2f(CHUNK_WORLD_SIZE * 0.5);
+    let distance = length(center - params.camera_pos.xz) / CHUNK_WORLD_SIZE;
+    var lod = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        if (distance > params.lod_distances[i]) {
+            lod += 1u;
+        }
+    }
+    lod = min(lod, params.lod_count - 1u);
+
+    let n = atomicAdd(&draws[lod].instance_count, 1u);
+    visible_chunks[lod * params.visible_stride + n] = slot;
+}
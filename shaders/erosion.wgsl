@@ -0,0 +1,320 @@
+// ============================================
+// EROSION
+// Hydraulic: pipe model, based on Mei et al., "Fast Hydraulic Erosion
+// Simulation and Visualization on GPU"
+// Thermal: talus slope relaxation, run after the hydraulic iterations
+//
+// Runs on a padded grid of CHUNK_SIZE + 2 * border samples per side, where
+// border includes the chunk's one-sample height apron. Every
+// pass reads at most one neighbour away (4 passes per hydraulic iteration,
+// 2 per thermal iteration), so the interior is unaffected by the grid edge
+// as long as border >= 1 + 4 * hydraulic + 2 * thermal. Neighbouring chunks
+// then agree on every shared sample.
+// ============================================
+
+// Set per pipeline from TerrainQuality
+override CHUNK_SIZE: u32 = 64u;  // Vertices per chunk edge
+override CHUNK_WORLD_SIZE: f32 = 256.0;  // World units per chunk
+const HEIGHT_APRON: u32 = 1u;  // Extra samples kept on each side of a chunk's height buffer
+override HEIGHT_GRID_SIZE: u32 = CHUNK_SIZE + 2u * HEIGHT_APRON;
+override CELL_SIZE: f32 = CHUNK_WORLD_SIZE / f32(CHUNK_SIZE - 1u);  // Distance between samples
+
+const DT: f32 = 0.25;
+const GRAVITY: f32 = 9.81;
+override PIPE_AREA: f32 = CELL_SIZE * CELL_SIZE;
+const DISSOLVE_RATE: f32 = 0.3;
+const DEPOSIT_RATE: f32 = 0.3;
+const MIN_TILT: f32 = 0.05;  // Keeps flat areas eroding a little
+
+// Must match ComputeParams in terrain.wgsl
+struct ComputeParams {
+    chunk_origin: vec2i,
+    terrain_scale: f32,
+    height_scale: f32,
+    octaves: u32,
+    warp_strength: f32,
+    height_variance: f32,
+    roughness: f32,
+    pattern_type: u32,
+    seed: u32,
+    border: u32,
+    erosion_rain: f32,
+    erosion_sediment_capacity: f32,
+    erosion_evaporation: f32,
+    thermal_talus: f32,  // Tangent of the talus angle
+    thermal_strength: f32,
+    slot: u32,
+    ao_radius: f32,
+}
+
+struct Cell {
+    flux: vec4f,        // Outflow to left, right, top, bottom neighbours (water, or material when thermal)
+    velocity: vec2f,
+    water: f32,
+    sediment: f32,
+    terrain_next: f32,  // Written by erode, applied by transport
+    sediment_next: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: ComputeParams;
+@group(0) @binding(1) var<storage, read_write> heights: array<f32>;  // Padded terrain from compute_height
+@group(0) @binding(2) var<storage, read_write> cells: array<Cell>;
+@group(0) @binding(3) var<storage, read_write> output: array<f32>;   // Chunk height buffer
+
+fn grid_size() -> i32 {
+    return i32(CHUNK_SIZE + 2u * params.border);
+}
+
+fn in_grid(x: i32, y: i32) -> bool {
+    let size = grid_size();
+    return x >= 0 && y >= 0 && x < size && y < size;
+}
+
+fn cell_index(x: i32, y: i32) -> u32 {
+    return u32(y * grid_size() + x);
+}
+
+fn clamped_index(x: i32, y: i32) -> u32 {
+    let last = grid_size() - 1;
+    return cell_index(clamp(x, 0, last), clamp(y, 0, last));
+}
+
+fn surface(x: i32, y: i32) -> f32 {
+    let i = cell_index(x, y);
+    return heights[i] + cells[i].water;
+}
+
+// Outgoing flux towards a neighbour; the grid edge is a closed wall
+fn outflow(current: f32, level: f32, nx: i32, ny: i32) -> f32 {
+    if (!in_grid(nx, ny)) {
+        return 0.0;
+    }
+    let dh = level - surface(nx, ny);
+    return max(0.0, current + DT * PIPE_AREA * GRAVITY * dh / CELL_SIZE);
+}
+
+fn neighbour_flux(x: i32, y: i32) -> vec4f {
+    if (!in_grid(x, y)) {
+        return vec4f(0.0);
+    }
+    return cells[cell_index(x, y)].flux;
+}
+
+@compute @workgroup_size(8, 8)
+fn erosion_init(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    cells[i] = Cell(vec4f(0.0), vec2f(0.0), params.erosion_rain, 0.0, heights[i], 0.0);
+}
+
+@compute @workgroup_size(8, 8)
+fn erosion_flux(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    let level = surface(x, y);
+    let old = cells[i].flux;
+    var flux = vec4f(
+        outflow(old.x, level, x - 1, y),
+        outflow(old.y, level, x + 1, y),
+        outflow(old.z, level, x, y - 1),
+        outflow(old.w, level, x, y + 1)
+    );
+
+    // Never drain more water than the cell holds
+    let total = flux.x + flux.y + flux.z + flux.w;
+    if (total > 0.0) {
+        flux *= min(1.0, cells[i].water * CELL_SIZE * CELL_SIZE / (total * DT));
+    }
+    cells[i].flux = flux;
+}
+
+@compute @workgroup_size(8, 8)
+fn erosion_water(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    let out_flux = cells[i].flux;
+    let in_left = neighbour_flux(x - 1, y).y;
+    let in_right = neighbour_flux(x + 1, y).x;
+    let in_top = neighbour_flux(x, y - 1).w;
+    let in_bottom = neighbour_flux(x, y + 1).z;
+
+    let inflow = in_left + in_right + in_top + in_bottom;
+    let outflow_total = out_flux.x + out_flux.y + out_flux.z + out_flux.w;
+    let old_water = cells[i].water;
+    let water = max(0.0, old_water + DT * (inflow - outflow_total) / (CELL_SIZE * CELL_SIZE));
+
+    // Velocity from the net flux through the cell
+    let mean_water = max((old_water + water) * 0.5, 1e-4);
+    let flow_x = (in_left - out_flux.x + out_flux.y - in_right) * 0.5;
+    let flow_y = (in_top - out_flux.z + out_flux.w - in_bottom) * 0.5;
+    var velocity = vec2f(flow_x, flow_y) / (CELL_SIZE * mean_water);
+
+    // Keep sediment transport within one cell per step
+    let max_speed = CELL_SIZE / DT;
+    let speed = length(velocity);
+    if (speed > max_speed) {
+        velocity *= max_speed / speed;
+    }
+
+    cells[i].water = water;
+    cells[i].velocity = velocity;
+}
+
+@compute @workgroup_size(8, 8)
+fn erosion_erode(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    let cell = cells[i];
+
+    // Local tilt from central differences
+    let gradient = vec2f(
+        heights[clamped_index(x + 1, y)] - heights[clamped_index(x - 1, y)],
+        heights[clamped_index(x, y + 1)] - heights[clamped_index(x, y - 1)]
+    ) / (2.0 * CELL_SIZE);
+    let slope_sq = dot(gradient, gradient);
+    let sin_tilt = max(sqrt(slope_sq / (1.0 + slope_sq)), MIN_TILT);
+
+    let capacity = params.erosion_sediment_capacity * sin_tilt * length(cell.velocity);
+    var terrain = heights[i];
+    var sediment = cell.sediment;
+    if (capacity > sediment) {
+        // Dissolve, but never dig deeper than the water covering the cell
+        let amount = min(DISSOLVE_RATE * (capacity - sediment), cell.water);
+        terrain -= amount;
+        sediment += amount;
+    } else {
+        let amount = DEPOSIT_RATE * (sediment - capacity);
+        terrain += amount;
+        sediment -= amount;
+    }
+
+    cells[i].terrain_next = terrain;
+    cells[i].sediment_next = sediment;
+}
+
+@compute @workgroup_size(8, 8)
+fn erosion_transport(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    let cell = cells[i];
+
+    // Semi-Lagrangian advection: take sediment from one step upstream.
+    // Only relative offsets are used so results don't depend on the cell's
+    // position in the padded grid (keeps chunk borders bit-identical).
+    let offset = -cell.velocity * DT / CELL_SIZE;
+    let base = min(floor(offset), vec2f(0.0));  // Samples stay within one cell
+    let t = offset - base;
+    let x0 = x + i32(base.x);
+    let y0 = y + i32(base.y);
+    let s00 = cells[clamped_index(x0, y0)].sediment_next;
+    let s10 = cells[clamped_index(x0 + 1, y0)].sediment_next;
+    let s01 = cells[clamped_index(x0, y0 + 1)].sediment_next;
+    let s11 = cells[clamped_index(x0 + 1, y0 + 1)].sediment_next;
+    let sediment = mix(mix(s00, s10, t.x), mix(s01, s11, t.x), t.y);
+
+    heights[i] = cell.terrain_next;
+    cells[i].sediment = sediment;
+    // Evaporate, then rain for the next iteration
+    cells[i].water = cell.water * (1.0 - params.erosion_evaporation) + params.erosion_rain;
+}
+
+// Drop suspended sediment back onto the terrain once the water is gone
+@compute @workgroup_size(8, 8)
+fn erosion_settle(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    heights[i] += cells[i].sediment;
+    cells[i].sediment = 0.0;
+}
+
+// Material a cell sheds towards a lower neighbour beyond the talus slope
+fn talus_excess(h: f32, nx: i32, ny: i32) -> f32 {
+    if (!in_grid(nx, ny)) {
+        return 0.0;
+    }
+    return max(0.0, h - heights[cell_index(nx, ny)] - params.thermal_talus * CELL_SIZE);
+}
+
+@compute @workgroup_size(8, 8)
+fn thermal_flux(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    let h = heights[i];
+    let excess = vec4f(
+        talus_excess(h, x - 1, y),
+        talus_excess(h, x + 1, y),
+        talus_excess(h, x, y - 1),
+        talus_excess(h, x, y + 1)
+    );
+
+    // Move part of the largest excess, split in proportion to each neighbour's.
+    // Halving it means two cells exchanging material end up level, not swapped.
+    let total = excess.x + excess.y + excess.z + excess.w;
+    var flux = vec4f(0.0);
+    if (total > 0.0) {
+        let largest = max(max(excess.x, excess.y), max(excess.z, excess.w));
+        flux = excess * (0.5 * params.thermal_strength * largest / total);
+    }
+    cells[i].flux = flux;
+}
+
+@compute @workgroup_size(8, 8)
+fn thermal_apply(@builtin(global_invocation_id) id: vec3u) {
+    let x = i32(id.x);
+    let y = i32(id.y);
+    if (!in_grid(x, y)) {
+        return;
+    }
+
+    let i = cell_index(x, y);
+    let out_flux = cells[i].flux;
+    let inflow = neighbour_flux(x - 1, y).y + neighbour_flux(x + 1, y).x
+        + neighbour_flux(x, y - 1).w + neighbour_flux(x, y + 1).z;
+    heights[i] += inflow - (out_flux.x + out_flux.y + out_flux.z + out_flux.w);
+}
+
+// Copy the chunk and its apron into its height buffer
+@compute @workgroup_size(8, 8)
+fn erosion_output(@builtin(global_invocation_id) id: vec3u) {
+    if (id.x >= HEIGHT_GRID_SIZE || id.y >= HEIGHT_GRID_SIZE) {
+        return;
+    }
+
+    let offset = i32(params.border - HEIGHT_APRON);
+    output[id.y * HEIGHT_GRID_SIZE + id.x] = heights[cell_index(i32(id.x) + offset, i32(id.y) + offset)];
+}
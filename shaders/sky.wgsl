@@ -7,6 +7,10 @@ struct SkyUniforms {
     view_proj: mat4x4f,
     camera_pos: vec3f,
     time: f32,
+    sun_angle: f32,        // Day/night cycle rotation of the suns about the Z axis
+    night_angle: f32,      // Rotation of the moons and stars
+    star_visibility: f32,
+    moon_visibility: f32,
 }
 
 struct SkyObject {
@@ -28,6 +32,7 @@ struct VertexOutput {
     @location(1) color: vec3f,
     @location(2) @interpolate(flat) object_type: u32,
     @location(3) seed: f32,
+    @location(4) visibility: f32,
 }
 
 // Billboard quad vertices (2 triangles)
@@ -40,6 +45,13 @@ const QUAD_VERTS: array<vec2f, 6> = array<vec2f, 6>(
     vec2f(1.0, 1.0),
 );
 
+// Must match rotate_z in daycycle.rs
+fn rotate_z(v: vec3f, angle: f32) -> vec3f {
+    let s = sin(angle);
+    let c = cos(angle);
+    return vec3f(v.x * c - v.y * s, v.x * s + v.y * c, v.z);
+}
+
 @vertex
 fn vs_main(
     @builtin(vertex_index) vertex_id: u32,
@@ -50,9 +62,19 @@ fn vs_main(
     let obj = objects[instance_id];
     let quad_vert = QUAD_VERTS[vertex_id];
 
+    // Move the object along its arc for the time of day
+    let is_sun = obj.object_type == 1u;
+    let position = rotate_z(obj.position, select(uniforms.night_angle, uniforms.sun_angle, is_sun));
+
+    // Objects the cycle has carried below the horizon are dropped (degenerate quad)
+    if (position.y < 0.0) {
+        out.position = vec4f(0.0);
+        return out;
+    }
+
     // Apply parallax offset based on camera position
     let parallax_offset = uniforms.camera_pos.xz * obj.parallax_factor * 0.001;
-    var base_pos = obj.position + vec3f(parallax_offset.x, 0.0, parallax_offset.y);
+    var base_pos = position + vec3f(parallax_offset.x, 0.0, parallax_offset.y);
 
     // Keep objects in upper hemisphere relative to camera
     base_pos.y = max(base_pos.y, uniforms.camera_pos.y + 5.0);
@@ -72,6 +94,11 @@ fn vs_main(
     out.color = obj.color;
     out.object_type = obj.object_type;
     out.seed = obj.seed;
+    switch obj.object_type {
+        case 0u: { out.visibility = uniforms.star_visibility; }
+        case 2u: { out.visibility = uniforms.moon_visibility; }
+        default: { out.visibility = 1.0; }
+    }
 
     return out;
 }
@@ -85,7 +112,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4f {
         case 0u: {
             // Star - point with twinkle
             let twinkle = sin(uniforms.time * 3.0 + in.seed) * 0.3 + 0.7;
-            let alpha = smoothstep(0.5, 0.0, dist) * twinkle;
+            let alpha = smoothstep(0.5, 0.0, dist) * twinkle * in.visibility;
 
             // Add glow
             let glow = smoothstep(0.5, 0.1, dist) * 0.5;
@@ -107,7 +134,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4f {
         }
         case 2u: {
             // Moon - solid disc with subtle gradient
-            let alpha = smoothstep(0.5, 0.45, dist);
+            let alpha = smoothstep(0.5, 0.45, dist) * in.visibility;
 
             // Slight shading gradient (left to right)
             let shade = 0.85 + 0.15 * (1.0 - in.uv.x);
@@ -2,8 +2,11 @@
 // CONSTANTS
 // ============================================
 
-const CHUNK_SIZE: u32 = 64u;  // Vertices per chunk edge
-const CHUNK_WORLD_SIZE: f32 = 256.0;  // World units per chunk
+// Set per pipeline from TerrainQuality
+override CHUNK_SIZE: u32 = 64u;  // Vertices per chunk edge
+override CHUNK_WORLD_SIZE: f32 = 256.0;  // World units per chunk
+const HEIGHT_APRON: u32 = 1u;  // Extra samples stored on each side of a chunk's height buffer
+override HEIGHT_GRID_SIZE: u32 = CHUNK_SIZE + 2u * HEIGHT_APRON;
 
 // ============================================
 // NOISE FUNCTIONS (Simplex 2D)
@@ -81,7 +84,7 @@ fn domain_warp(p: vec2f, warp_strength: f32) -> vec2f {
 // ============================================
 
 struct ComputeParams {
-    chunk_offset: vec2f,   // World position of chunk origin
+    chunk_origin: vec2i,   // Global sample index of the chunk's first vertex
     terrain_scale: f32,    // Horizontal scale for noise
     height_scale: f32,     // Vertical scale for output
     octaves: u32,          // FBM octaves
@@ -90,12 +93,30 @@ struct ComputeParams {
     roughness: f32,        // FBM persistence
     pattern_type: u32,     // 0=standard, 1=ridged, 2=islands, 3=valleys, 4=terraced
     seed: u32,             // Random seed for terrain variation
-    _pad0: f32,
-    _pad1: f32,
+    border: u32,           // Extra samples generated on each side (apron + erosion)
+    erosion_rain: f32,
+    erosion_sediment_capacity: f32,
+    erosion_evaporation: f32,
+    thermal_talus: f32,    // Tangent of the talus angle
+    thermal_strength: f32,
+    slot: u32,             // Chunk slot the heights belong to
+    ao_radius: f32,        // AO search radius in samples; 0 skips the bake
+}
+
+// Must match ChunkInfo in culling.wgsl
+struct ChunkInfo {
+    world_offset: vec2f,
+    height_offset: u32,  // First sample of the chunk in the shared height buffer
+    ready: u32,
+    min_height: f32,     // Written by reduce_height_bounds
+    max_height: f32,
+    ao_offset: u32,      // First value of the chunk in the shared AO buffer
+    _padding: u32,
 }
 
 @group(0) @binding(0) var<uniform> compute_params: ComputeParams;
 @group(0) @binding(1) var<storage, read_write> height_buffer: array<f32>;
+@group(0) @binding(2) var<storage, read_write> chunk_bounds: array<ChunkInfo>;  // reduce_height_bounds only
 
 // FBM with configurable roughness
 fn fbm_rough(p: vec2f, octaves: i32, roughness: f32) -> f32 {
@@ -122,15 +143,18 @@ fn island_falloff(world_pos: vec2f, center: vec2f, radius: f32) -> f32 {
 
 @compute @workgroup_size(8, 8)
 fn compute_height(@builtin(global_invocation_id) id: vec3u) {
-    if (id.x >= CHUNK_SIZE || id.y >= CHUNK_SIZE) {
+    let grid_size = CHUNK_SIZE + 2u * compute_params.border;
+    if (id.x >= grid_size || id.y >= grid_size) {
         return;
     }
 
-    let index = id.y * CHUNK_SIZE + id.x;
+    let index = id.y * grid_size + id.x;
 
-    // Calculate world position of this vertex
-    let local_uv = vec2f(f32(id.x), f32(id.y)) / f32(CHUNK_SIZE - 1u);
-    let world_pos = compute_params.chunk_offset + local_uv * CHUNK_WORLD_SIZE;
+    // Calculate world position from the global sample index, so chunks that
+    // share a sample (edges, erosion borders) compute bit-identical heights
+    let border = i32(compute_params.border);
+    let sample = compute_params.chunk_origin + vec2i(id.xy) - vec2i(border, border);
+    let world_pos = vec2f(sample) * (CHUNK_WORLD_SIZE / f32(CHUNK_SIZE - 1u));
 
     // Apply seed offset to create different terrain for each seed
     // Using small multipliers to stay in a similar noise region (preserves terrain style)
@@ -223,7 +247,8 @@ fn compute_height(@builtin(global_invocation_id) id: vec3u) {
             height += fbm_rough(noise_pos * 4.0, 3, roughness) * step_size * 0.2;
         }
         case 5u: {
-            // Blocky/plateau terrain - uses distant noise region for unique character
+            // Blocky/plateau terrain - uses distant noise region for unique character.
+            // Must match BLOCKY_OFFSET in sampler.rs
             let blocky_offset = vec2f(1000000.0, 1337000.0);
             let blocky_pos = noise_pos + blocky_offset;
             let warped_pos = domain_warp(blocky_pos * 0.5, compute_params.warp_strength);
@@ -247,6 +272,94 @@ fn compute_height(@builtin(global_invocation_id) id: vec3u) {
     height_buffer[index] = height;
 }
 
+// ============================================
+// COMPUTE SHADER - HEIGHT BOUNDS
+// One workgroup per chunk: each thread scans a strided share of the chunk's
+// samples (apron excluded), then a tree reduction in workgroup memory.
+// ============================================
+
+const BOUNDS_WORKGROUP_SIZE: u32 = 256u;
+
+var<workgroup> bounds_min: array<f32, BOUNDS_WORKGROUP_SIZE>;
+var<workgroup> bounds_max: array<f32, BOUNDS_WORKGROUP_SIZE>;
+
+@compute @workgroup_size(256)
+fn reduce_height_bounds(@builtin(local_invocation_index) index: u32) {
+    var low = 3.0e38;
+    var high = -3.0e38;
+    for (var i = index; i < CHUNK_SIZE * CHUNK_SIZE; i += BOUNDS_WORKGROUP_SIZE) {
+        let x = i % CHUNK_SIZE + HEIGHT_APRON;
+        let y = i / CHUNK_SIZE + HEIGHT_APRON;
+        let height = height_buffer[y * HEIGHT_GRID_SIZE + x];
+        low = min(low, height);
+        high = max(high, height);
+    }
+    bounds_min[index] = low;
+    bounds_max[index] = high;
+    workgroupBarrier();
+
+    for (var stride = BOUNDS_WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        if (index < stride) {
+            bounds_min[index] = min(bounds_min[index], bounds_min[index + stride]);
+            bounds_max[index] = max(bounds_max[index], bounds_max[index + stride]);
+        }
+        workgroupBarrier();
+    }
+
+    if (index == 0u) {
+        chunk_bounds[compute_params.slot].min_height = bounds_min[0];
+        chunk_bounds[compute_params.slot].max_height = bounds_max[0];
+    }
+}
+
+// ============================================
+// COMPUTE SHADER - AMBIENT OCCLUSION BAKE
+// Horizon-based: march the padded height grid outwards in several directions
+// and take the highest elevation angle seen in each. Runs after erosion, on
+// the same grid, so neighbouring chunks agree on their shared edge samples.
+// ============================================
+
+const AO_DIRECTIONS: u32 = 8u;
+const AO_STEPS: u32 = 12u;
+const TAU: f32 = 6.28318530718;
+
+@group(0) @binding(3) var<storage, read_write> ao_output: array<f32>;  // Chunk's range of the AO buffer
+
+fn padded_height(x: i32, y: i32) -> f32 {
+    let last = i32(CHUNK_SIZE + 2u * compute_params.border) - 1;
+    let grid_size = u32(last + 1);
+    return height_buffer[u32(clamp(y, 0, last)) * grid_size + u32(clamp(x, 0, last))];
+}
+
+@compute @workgroup_size(8, 8)
+fn bake_ambient_occlusion(@builtin(global_invocation_id) id: vec3u) {
+    if (id.x >= CHUNK_SIZE || id.y >= CHUNK_SIZE) {
+        return;
+    }
+
+    let border = i32(compute_params.border);
+    let center = vec2i(id.xy) + vec2i(border, border);
+    let height = padded_height(center.x, center.y);
+    let spacing = CHUNK_WORLD_SIZE / f32(CHUNK_SIZE - 1u);
+
+    // Sine of each direction's horizon angle is the share of that slice of sky it hides
+    var occlusion = 0.0;
+    for (var d = 0u; d < AO_DIRECTIONS; d++) {
+        let angle = f32(d) * TAU / f32(AO_DIRECTIONS);
+        let dir = vec2f(cos(angle), sin(angle));
+        var horizon = 0.0;  // Tangent of the horizon angle; flat ground hides nothing
+        for (var s = 1u; s <= AO_STEPS; s++) {
+            let distance = compute_params.ao_radius * f32(s) / f32(AO_STEPS);
+            let p = vec2i(round(vec2f(center) + dir * distance));
+            let rise = padded_height(p.x, p.y) - height;
+            horizon = max(horizon, rise / (distance * spacing));
+        }
+        occlusion += horizon * inverseSqrt(1.0 + horizon * horizon);
+    }
+
+    ao_output[id.y * CHUNK_SIZE + id.x] = occlusion / f32(AO_DIRECTIONS);
+}
+
 // ============================================
 // VERTEX SHADER - TERRAIN RENDERING
 // ============================================
@@ -257,18 +370,18 @@ struct CameraUniforms {
     _padding: f32,
 }
 
-struct ChunkUniforms {
-    chunk_offset: vec2f,
-    _padding: vec2f,
-}
 
 @group(0) @binding(0) var<uniform> camera: CameraUniforms;
-@group(1) @binding(0) var<uniform> chunk: ChunkUniforms;
-@group(1) @binding(1) var<storage, read> heights: array<f32>;
+@group(1) @binding(0) var<storage, read> chunks: array<ChunkInfo>;
+@group(1) @binding(1) var<storage, read> heights: array<f32>;     // Every slot's heights, at height_offset
+@group(1) @binding(2) var<storage, read> visible_chunks: array<u32>;  // Slots written by cull_chunks
+@group(1) @binding(3) var<storage, read> ambient_occlusion: array<f32>;  // Every slot's baked AO, at ao_offset
 
 struct VertexInput {
     @location(0) local_uv: vec2f,  // 0..1 range within chunk
+    @location(1) skirt_depth: f32, // Drop below the surface (LOD crack skirts only)
     @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,  // One instance per visible chunk
 }
 
 struct VertexOutput {
@@ -276,46 +389,66 @@ struct VertexOutput {
     @location(0) world_pos: vec3f,
     @location(1) normal: vec3f,
     @location(2) height: f32,
+    @location(3) occlusion: f32,  // Baked AO, 0 = open sky
 }
 
-fn get_height(x: i32, y: i32) -> f32 {
-    let cx = clamp(x, 0, i32(CHUNK_SIZE) - 1);
-    let cy = clamp(y, 0, i32(CHUNK_SIZE) - 1);
-    return heights[u32(cy) * CHUNK_SIZE + u32(cx)];
+// Heights include a one-sample apron, so x and y may range over -1..=CHUNK_SIZE
+fn get_height(base: u32, x: i32, y: i32) -> f32 {
+    let apron = i32(HEIGHT_APRON);
+    return heights[base + u32(y + apron) * HEIGHT_GRID_SIZE + u32(x + apron)];
 }
 
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
+    let chunk = chunks[visible_chunks[in.instance_index]];
+    let base = chunk.height_offset;
 
     // Grid indices from UV
     let grid_x = i32(in.local_uv.x * f32(CHUNK_SIZE - 1u) + 0.5);
     let grid_y = i32(in.local_uv.y * f32(CHUNK_SIZE - 1u) + 0.5);
 
     // Get height for this vertex
-    let height = get_height(grid_x, grid_y);
+    let height = get_height(base, grid_x, grid_y);
 
     // World position
-    let world_xz = chunk.chunk_offset + in.local_uv * CHUNK_WORLD_SIZE;
-    out.world_pos = vec3f(world_xz.x, height, world_xz.y);
+    let world_xz = chunk.world_offset + in.local_uv * CHUNK_WORLD_SIZE;
+    out.world_pos = vec3f(world_xz.x, height - in.skirt_depth, world_xz.y);
 
     // Compute normal from neighboring heights
     let step = CHUNK_WORLD_SIZE / f32(CHUNK_SIZE - 1u);
-    let h_left = get_height(grid_x - 1, grid_y);
-    let h_right = get_height(grid_x + 1, grid_y);
-    let h_down = get_height(grid_x, grid_y - 1);
-    let h_up = get_height(grid_x, grid_y + 1);
+    let h_left = get_height(base, grid_x - 1, grid_y);
+    let h_right = get_height(base, grid_x + 1, grid_y);
+    let h_down = get_height(base, grid_x, grid_y - 1);
+    let h_up = get_height(base, grid_x, grid_y + 1);
 
     let dx = (h_right - h_left) / (2.0 * step);
     let dz = (h_up - h_down) / (2.0 * step);
     out.normal = normalize(vec3f(-dx, 1.0, -dz));
 
     out.height = height;
+    out.occlusion = ambient_occlusion[chunk.ao_offset + u32(grid_y) * CHUNK_SIZE + u32(grid_x)];
     out.clip_position = camera.view_proj * vec4f(out.world_pos, 1.0);
 
     return out;
 }
 
+// Shadow depth pass: camera holds the cascade's light matrix, and the instance
+// index is the chunk slot itself (draws cover runs of consecutive slots)
+@vertex
+fn vs_shadow(in: VertexInput) -> @builtin(position) vec4f {
+    let chunk = chunks[in.instance_index];
+    if (chunk.ready == 0u) {
+        return vec4f(0.0);  // Degenerate, nothing rasterized
+    }
+
+    let grid_x = i32(in.local_uv.x * f32(CHUNK_SIZE - 1u) + 0.5);
+    let grid_y = i32(in.local_uv.y * f32(CHUNK_SIZE - 1u) + 0.5);
+    let height = get_height(chunk.height_offset, grid_x, grid_y);
+    let world_xz = chunk.world_offset + in.local_uv * CHUNK_WORLD_SIZE;
+    return camera.view_proj * vec4f(world_xz.x, height - in.skirt_depth, world_xz.y, 1.0);
+}
+
 // ============================================
 // FRAGMENT SHADER - TERRAIN COLORING
 // ============================================
@@ -331,20 +464,82 @@ struct ColorParams {
     color_sky: vec4f,
     color_sky_top: vec4f,
     color_sky_horizon: vec4f,
+    sun_direction: vec4f,  // Normalized, towards the light
+    sun_color: vec4f,      // Color times intensity
     ambient: f32,
     fog_start: f32,
     fog_distance: f32,
-    _padding: f32,
+    ao_strength: f32,
+    sea_level: f32,
+    water_enabled: u32,  // Below sea level is seabed under the water pass, not water bands
+    _padding: vec2f,
 }
 
 @group(2) @binding(0) var<uniform> colors: ColorParams;
 
-const SUN_DIR: vec3f = vec3f(0.4, 0.7, 0.5);
+// ============================================
+// SHADOWS - cascaded shadow maps with PCF
+// ============================================
+
+const MAX_CASCADES: u32 = 4u;  // Must match MAX_CASCADES in shadows.rs
+const SHADOW_NORMAL_OFFSET: f32 = 1.5;  // Texels to push the lookup along the normal, against acne
+
+struct ShadowParams {
+    light_view_proj: array<mat4x4f, MAX_CASCADES>,
+    split_depths: vec4f,    // Far view depth of each cascade
+    texel_sizes: vec4f,     // World units per texel of each cascade
+    camera_forward: vec4f,  // w unused
+    cascade_count: u32,
+    enabled: u32,
+    _padding: vec2u,
+}
+
+@group(3) @binding(0) var shadow_map: texture_depth_2d_array;
+@group(3) @binding(1) var shadow_sampler: sampler_comparison;
+@group(3) @binding(2) var<uniform> shadow: ShadowParams;
+
+// 1 where the sun reaches the point, 0 in full shadow
+fn shadow_factor(world_pos: vec3f, normal: vec3f) -> f32 {
+    if (shadow.enabled == 0u) {
+        return 1.0;
+    }
+
+    // Pick the cascade by view depth
+    let depth = dot(world_pos - camera.camera_pos, shadow.camera_forward.xyz);
+    var cascade = 0u;
+    for (var i = 0u; i < shadow.cascade_count; i++) {
+        if (depth > shadow.split_depths[i]) {
+            cascade = i + 1u;
+        }
+    }
+    if (cascade >= shadow.cascade_count) {
+        return 1.0;
+    }
+
+    let offset_pos = world_pos + normal * shadow.texel_sizes[cascade] * SHADOW_NORMAL_OFFSET;
+    let light_pos = shadow.light_view_proj[cascade] * vec4f(offset_pos, 1.0);
+    let ndc = light_pos.xyz / light_pos.w;
+    let uv = ndc.xy * vec2f(0.5, -0.5) + 0.5;
+    if (any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) || ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    // 3x3 PCF; each tap is also bilinearly filtered by the comparison sampler
+    let texel = 1.0 / f32(textureDimensions(shadow_map).x);
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2f(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, cascade, ndc.z);
+        }
+    }
+    return lit / 9.0;
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
     let normal = normalize(in.normal);
-    let sun_dir = normalize(SUN_DIR);
+    let sun_dir = colors.sun_direction.xyz;
 
     // Calculate slope (0 = flat, 1 = vertical)
     let slope = 1.0 - normal.y;
@@ -360,11 +555,15 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4f {
     let dark_rock = rock * 0.7; // Derive from rock
     let snow = colors.color_snow.rgb;
 
-    // Height-based terrain coloring
+    // Height-based terrain coloring, measured from sea level
     var base_color: vec3f;
-    let h = in.height;
+    let h = in.height - colors.sea_level;
 
-    if (h < -100.0) {
+    if (colors.water_enabled != 0u && h < 0.0) {
+        // Seabed - sand darkening to rock with depth; water.wgsl colors the water above
+        let t = clamp(-h / 100.0, 0.0, 1.0);
+        base_color = mix(sand, dark_rock, t);
+    } else if (h < -100.0) {
         // Abyss - deepest water
         base_color = abyss;
     } else if (h < -5.0) {
@@ -415,8 +614,11 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4f {
     // Add some wrap lighting for softer shadows
     let wrap_light = (ndotl + 0.3) / 1.3;
 
-    // Final lighting
-    let lighting = colors.ambient + (1.0 - colors.ambient) * wrap_light;
+    // Final lighting; shadowed areas keep only the ambient term, and
+    // valleys and crevices are darkened by the baked occlusion
+    let direct = wrap_light * shadow_factor(in.world_pos, normal);
+    let ao = 1.0 - colors.ao_strength * in.occlusion;
+    let lighting = (colors.ambient + (1.0 - colors.ambient) * direct * colors.sun_color.rgb) * ao;
 
     // Apply fog based on distance from camera, attenuated by camera height
     let dist = length(in.world_pos - camera.camera_pos);
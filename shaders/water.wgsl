@@ -0,0 +1,184 @@
+// ============================================
+// WATER SURFACE
+// A flat sheet at sea level around the camera, drawn after the terrain. The
+// terrain's depth is sampled to hide the water behind the terrain, and to find
+// how much water lies between the surface and the ground behind it.
+// ============================================
+
+const TAU: f32 = 6.28318530718;
+const GRAVITY: f32 = 9.81;
+const FRESNEL_F0: f32 = 0.02;          // Reflectance of water looking straight down
+const WAVE_FADE_DISTANCE: f32 = 1500.0;  // Waves flatten out with distance to avoid shimmering
+const SPECULAR_POWER: f32 = 400.0;
+
+// Direction (xy), wavelength in world units (z) and share of the wave slope (w)
+const WAVES: array<vec4f, 4> = array<vec4f, 4>(
+    vec4f(0.8, 0.6, 61.0, 0.4),
+    vec4f(-0.5, 0.87, 31.0, 0.3),
+    vec4f(0.97, -0.26, 17.0, 0.2),
+    vec4f(-0.2, -0.98, 9.0, 0.1),
+);
+
+struct CameraUniforms {
+    view_proj: mat4x4f,
+    camera_pos: vec3f,
+    _padding: f32,
+}
+
+// Must match WaterParams in water.rs
+struct WaterParams {
+    inverse_view_proj: mat4x4f,
+    sea_level: f32,
+    extent: f32,          // Half the side of the water sheet
+    time: f32,
+    wave_strength: f32,   // Steepest wave slope
+    wave_speed: f32,
+    clarity: f32,         // Distance light travels through the water before fading to 1/e
+    foam_width: f32,      // Water depth below which foam appears
+    _padding: f32,
+}
+
+// Must match ColorParams in terrain.wgsl
+struct ColorParams {
+    color_abyss: vec4f,
+    color_deep_water: vec4f,
+    color_shallow_water: vec4f,
+    color_sand: vec4f,
+    color_grass: vec4f,
+    color_rock: vec4f,
+    color_snow: vec4f,
+    color_sky: vec4f,
+    color_sky_top: vec4f,
+    color_sky_horizon: vec4f,
+    sun_direction: vec4f,
+    sun_color: vec4f,
+    ambient: f32,
+    fog_start: f32,
+    fog_distance: f32,
+    ao_strength: f32,
+    sea_level: f32,
+    water_enabled: u32,
+    _padding: vec2f,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniforms;
+@group(1) @binding(0) var<uniform> water: WaterParams;
+@group(1) @binding(1) var scene_depth: texture_2d<f32>;  // Terrain depth, read as unfilterable floats
+@group(2) @binding(0) var<uniform> colors: ColorParams;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) world_pos: vec3f,
+}
+
+const QUAD_CORNERS: array<vec2f, 6> = array<vec2f, 6>(
+    vec2f(-1.0, -1.0),
+    vec2f(-1.0, 1.0),
+    vec2f(1.0, -1.0),
+    vec2f(1.0, -1.0),
+    vec2f(-1.0, 1.0),
+    vec2f(1.0, 1.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let xz = camera.camera_pos.xz + QUAD_CORNERS[vertex_index] * water.extent;
+    out.world_pos = vec3f(xz.x, water.sea_level, xz.y);
+    out.clip_position = camera.view_proj * vec4f(out.world_pos, 1.0);
+    return out;
+}
+
+// Surface normal from a sum of travelling sine waves, each moving at the
+// deep-water speed for its wavelength
+fn wave_normal(xz: vec2f, strength: f32) -> vec3f {
+    var slope = vec2f(0.0);
+    for (var i = 0u; i < 4u; i++) {
+        let wave = WAVES[i];
+        let k = TAU / wave.z;
+        let phase = k * dot(wave.xy, xz) - sqrt(GRAVITY * k) * water.time * water.wave_speed;
+        slope += wave.xy * (wave.w * cos(phase));
+    }
+    slope *= strength;
+    return normalize(vec3f(-slope.x, 1.0, -slope.y));
+}
+
+// World position of the terrain behind a pixel, from the depth buffer
+fn world_from_depth(pixel: vec2f, depth: f32) -> vec3f {
+    let size = vec2f(textureDimensions(scene_depth));
+    let uv = pixel / size;
+    let ndc = vec4f(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let world = water.inverse_view_proj * ndc;
+    return world.xyz / world.w;
+}
+
+// Premultiplied: the ground behind shows through by 1 - alpha
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let to_surface = in.world_pos - camera.camera_pos;
+    let view_distance = length(to_surface);
+    let view_dir = to_surface / view_distance;
+    let sun_dir = colors.sun_direction.xyz;
+    let sun_color = colors.sun_color.rgb;
+
+    // Water between the surface and the ground behind it. Nothing behind
+    // (cleared depth) means open ocean.
+    let depth = textureLoad(scene_depth, vec2i(in.clip_position.xy), 0).r;
+    if (in.clip_position.z >= depth) {
+        discard;  // Behind the terrain
+    }
+    var thickness = 1e6;  // Along the view ray
+    var water_depth = 1e6;  // Straight down
+    if (depth < 1.0) {
+        let ground = world_from_depth(in.clip_position.xy, depth);
+        thickness = distance(ground, in.world_pos);
+        water_depth = max(water.sea_level - ground.y, 0.0);
+    }
+
+    var normal = wave_normal(in.world_pos.xz, water.wave_strength / (1.0 + view_distance / WAVE_FADE_DISTANCE));
+    if (camera.camera_pos.y < water.sea_level) {
+        normal = -normal;  // Seen from below
+    }
+
+    // Reflected sky, using the same gradient as the fog
+    let reflected = reflect(view_dir, normal);
+    let sky_blend = smoothstep(-0.1, 0.5, reflected.y);
+    let sky = mix(colors.color_sky_horizon.rgb, colors.color_sky_top.rgb, sky_blend);
+    let specular = pow(max(dot(reflected, sun_dir), 0.0), SPECULAR_POWER) * sun_color;
+
+    let cos_view = max(dot(-view_dir, normal), 0.0);
+    let fresnel = FRESNEL_F0 + (1.0 - FRESNEL_F0) * pow(1.0 - cos_view, 5.0);
+
+    // Scattered light from within the water: shallow to deep to abyss with depth,
+    // lit by the ambient and the sun above
+    let lighting = colors.ambient + (1.0 - colors.ambient) * max(sun_dir.y, 0.0) * sun_color;
+    let depth_fade = 1.0 - exp(-water_depth / water.clarity);
+    let body = mix(
+        mix(colors.color_shallow_water.rgb, colors.color_deep_water.rgb, depth_fade),
+        colors.color_abyss.rgb,
+        depth_fade * depth_fade
+    ) * lighting;
+    let transmittance = exp(-thickness / water.clarity);
+
+    // Foam along the shore, broken up by slowly drifting bands
+    var foam = 0.0;
+    if (water.foam_width > 0.0) {
+        let edge = 1.0 - clamp(water_depth / water.foam_width, 0.0, 1.0);
+        let p = in.world_pos.xz;
+        let bands = sin(dot(p, vec2f(0.37, 0.21)) + water.time * 1.3) * sin(dot(p, vec2f(-0.17, 0.41)) - water.time);
+        foam = clamp(edge * edge * (1.2 + 0.8 * bands), 0.0, 1.0);
+    }
+
+    let surface = (1.0 - fresnel) * (1.0 - transmittance) * body + fresnel * sky + specular;
+    var color = mix(surface, lighting, foam);
+    let alpha = 1.0 - (1.0 - fresnel) * transmittance * (1.0 - foam);
+
+    // Same fog as the terrain, over the water's own contribution
+    let height_factor = 1.0 - clamp((camera.camera_pos.y - 500.0) / 300.0, 0.0, 1.0);
+    let fog_factor = clamp((view_distance - colors.fog_start) / colors.fog_distance, 0.0, 0.8) * height_factor;
+    let fog_blend = smoothstep(-0.1, 0.5, view_dir.y);
+    let fog_color = mix(colors.color_sky_horizon.rgb, colors.color_sky_top.rgb, fog_blend);
+    color = mix(color, fog_color * alpha, fog_factor);
+
+    return vec4f(color, alpha);
+}
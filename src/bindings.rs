@@ -0,0 +1,455 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use glam::Vec3;
+use js_sys::Math;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+use crate::camera::CameraSettings;
+use crate::camera_path::CameraPath;
+use crate::daycycle::DayCycleSettings;
+use crate::input;
+use crate::particles::ParticleSettings;
+use crate::presets;
+use crate::shadows::ShadowSettings;
+use crate::sky::SkySettings;
+use crate::terrain::{TerrainQuality, TerrainSettings};
+use crate::utils;
+use crate::webgpu::GpuState;
+use crate::AppState;
+
+// Global state for JS access
+thread_local! {
+    static APP_STATE: RefCell<Option<Rc<RefCell<AppState>>>> = const { RefCell::new(None) };
+}
+
+/// Helper to access APP_STATE with mutable access and automatic error handling
+fn with_app_state_mut<F, T>(f: F) -> Result<T, JsValue>
+where
+    F: FnOnce(&mut AppState) -> T,
+{
+    APP_STATE.with(|s| {
+        if let Some(state) = s.borrow().as_ref() {
+            Ok(f(&mut state.borrow_mut()))
+        } else {
+            Err(JsValue::from_str("App not initialized"))
+        }
+    })
+}
+
+/// Helper to access APP_STATE with read-only access and automatic error handling
+fn with_app_state<F, T>(f: F) -> Result<T, JsValue>
+where
+    F: FnOnce(&AppState) -> T,
+{
+    APP_STATE.with(|s| {
+        if let Some(state) = s.borrow().as_ref() {
+            Ok(f(&state.borrow()))
+        } else {
+            Err(JsValue::from_str("App not initialized"))
+        }
+    })
+}
+
+/// Error for terrain queries whose heights haven't been read back from the GPU
+fn not_loaded(x: f32, z: f32) -> JsValue {
+    JsValue::from_str(&format!("Terrain heights at ({}, {}) aren't loaded yet", x, z))
+}
+
+#[wasm_bindgen(start)]
+pub async fn run() {
+    utils::init();
+
+    log::info!("Starting Procedural Terrain Simulator...");
+
+    // Get canvas from DOM
+    let window = web_sys::window().expect("No window");
+    let document = window.document().expect("No document");
+    let canvas = document
+        .get_element_by_id("canvas")
+        .expect("No canvas element with id 'canvas'")
+        .dyn_into::<HtmlCanvasElement>()
+        .expect("Element is not a canvas");
+
+    // Initialize app state with a random seed, like clicking a preset button
+    let seed = (Math::random() * 1000000.0) as u32;
+    let state = match GpuState::new(&canvas)
+        .await
+        .and_then(|gpu| AppState::new(gpu, seed))
+    {
+        Ok(state) => Rc::new(RefCell::new(state)),
+        Err(e) => {
+            log::error!("Failed to initialize: {}", e);
+            show_error(&document, &format!("WebGPU initialization failed: {}", e));
+            return;
+        }
+    };
+
+    // Store state globally for JS access
+    APP_STATE.with(|s| {
+        *s.borrow_mut() = Some(Rc::clone(&state));
+    });
+
+    // Setup input handlers
+    input::setup_input_handlers(&canvas, Rc::clone(&state));
+
+    // Setup resize handler
+    setup_resize_handler(&canvas, Rc::clone(&state));
+
+    // Start the frame loop
+    start_frame_loop(state);
+
+    log::info!("Initialization complete. Click canvas to capture mouse.");
+}
+
+fn show_error(document: &web_sys::Document, message: &str) {
+    if let Some(error_el) = document.get_element_by_id("error") {
+        error_el.set_text_content(Some(message));
+        let _ = error_el
+            .dyn_ref::<web_sys::HtmlElement>()
+            .map(|el| el.style().set_property("display", "block"));
+    }
+}
+
+fn setup_resize_handler(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppState>>) {
+    let canvas_clone = canvas.clone();
+    let closure = Closure::wrap(Box::new(move || {
+        let Some(window) = web_sys::window() else { return };
+        let logical_width = window
+            .inner_width()
+            .ok()
+            .and_then(|v| v.as_f64())
+            .unwrap_or(800.0);
+        let logical_height = window
+            .inner_height()
+            .ok()
+            .and_then(|v| v.as_f64())
+            .unwrap_or(600.0);
+        let dpr = window.device_pixel_ratio();
+        let physical_width = (logical_width * dpr).round() as u32;
+        let physical_height = (logical_height * dpr).round() as u32;
+
+        canvas_clone.set_width(physical_width);
+        canvas_clone.set_height(physical_height);
+
+        if let Some(element) = canvas_clone.dyn_ref::<web_sys::HtmlElement>() {
+            let _ = element.style().set_property("width", &format!("{logical_width}px"));
+            let _ = element
+                .style()
+                .set_property("height", &format!("{logical_height}px"));
+        }
+
+        state.borrow_mut().resize(physical_width, physical_height);
+    }) as Box<dyn FnMut()>);
+
+    if let Some(window) = web_sys::window() {
+        let _ = window.add_event_listener_with_callback("resize", closure.as_ref().unchecked_ref());
+    }
+    closure.forget();
+}
+
+/// Self-referencing requestAnimationFrame callback
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+fn start_frame_loop(state: Rc<RefCell<AppState>>) {
+    let f: FrameCallback = Rc::new(RefCell::new(None));
+    let g = f.clone();
+
+    *g.borrow_mut() = Some(Closure::new(move |timestamp: f64| {
+        {
+            let mut state = state.borrow_mut();
+            // update() returns dt, which render() needs for particle simulation
+            let dt = state.update(timestamp);
+            state.render(dt);
+        }
+
+        // Request next frame
+        if let Some(window) = web_sys::window() {
+            if let Some(closure) = f.borrow().as_ref() {
+                let _ = window.request_animation_frame(closure.as_ref().unchecked_ref());
+            }
+        }
+    }));
+
+    if let Some(window) = web_sys::window() {
+        if let Some(closure) = g.borrow().as_ref() {
+            let _ = window.request_animation_frame(closure.as_ref().unchecked_ref());
+        }
+    }
+}
+
+fn default_settings_to_js<T, F>(map_fn: F, label: &str) -> Result<JsValue, JsValue>
+where
+    T: Serialize + Default,
+    F: Fn(presets::FullPreset) -> T,
+{
+    let settings = presets::get_default_preset()
+        .map(map_fn)
+        .unwrap_or_default();
+    serde_wasm_bindgen::to_value(&settings)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize {}: {}", label, e)))
+}
+
+/// Update terrain settings from JavaScript
+/// Called with a JS object containing settings fields
+#[wasm_bindgen]
+pub fn update_terrain_settings(settings_js: JsValue) -> Result<(), JsValue> {
+    let settings: TerrainSettings = serde_wasm_bindgen::from_value(settings_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse settings: {}", e)))?;
+    with_app_state_mut(|state| state.update_terrain_settings(settings))
+}
+
+/// Get current terrain settings as a JS object
+#[wasm_bindgen]
+pub fn get_terrain_settings() -> Result<JsValue, JsValue> {
+    let settings = with_app_state(|state| state.get_terrain_settings().clone())?;
+    serde_wasm_bindgen::to_value(&settings)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize settings: {}", e)))
+}
+
+/// Change chunk resolution and view radius; rebuilds the chunk pool
+#[wasm_bindgen]
+pub fn set_terrain_quality(quality_js: JsValue) -> Result<(), JsValue> {
+    let quality: TerrainQuality = serde_wasm_bindgen::from_value(quality_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse terrain quality: {}", e)))?;
+    with_app_state_mut(|state| state.set_terrain_quality(quality))?.map_err(|e| JsValue::from_str(&e))
+}
+
+/// Get current terrain quality as a JS object
+#[wasm_bindgen]
+pub fn get_terrain_quality() -> Result<JsValue, JsValue> {
+    let quality = with_app_state(|state| *state.get_terrain_quality())?;
+    serde_wasm_bindgen::to_value(&quality)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize terrain quality: {}", e)))
+}
+
+/// Get default terrain quality (before app initialization)
+#[wasm_bindgen]
+pub fn get_default_terrain_quality() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&TerrainQuality::default())
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize terrain quality: {}", e)))
+}
+
+/// Update shadow settings (enabled, resolution, cascade_count, max_distance) from JavaScript
+#[wasm_bindgen]
+pub fn update_shadow_settings(settings_js: JsValue) -> Result<(), JsValue> {
+    let settings: ShadowSettings = serde_wasm_bindgen::from_value(settings_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse shadow settings: {}", e)))?;
+    with_app_state_mut(|state| state.update_shadow_settings(settings))?.map_err(|e| JsValue::from_str(&e))
+}
+
+/// Get current shadow settings as a JS object
+#[wasm_bindgen]
+pub fn get_shadow_settings() -> Result<JsValue, JsValue> {
+    let settings = with_app_state(|state| *state.get_shadow_settings())?;
+    serde_wasm_bindgen::to_value(&settings)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize shadow settings: {}", e)))
+}
+
+/// Terrain height at a world position, from the heights generated on the GPU
+/// (erosion included). These are read back a few frames after each chunk is
+/// generated, so this errors for positions outside the loaded chunks or whose
+/// chunk hasn't been read back yet.
+#[wasm_bindgen]
+pub fn get_terrain_height(x: f32, z: f32) -> Result<f32, JsValue> {
+    with_app_state(|state| state.terrain_height_at(x, z))?.ok_or_else(|| not_loaded(x, z))
+}
+
+/// Terrain surface normal at a world position as [x, y, z]. Errors like
+/// `get_terrain_height` when the heights around the position aren't available.
+#[wasm_bindgen]
+pub fn get_terrain_normal(x: f32, z: f32) -> Result<Vec<f32>, JsValue> {
+    let normal = with_app_state(|state| state.terrain_normal_at(x, z))?.ok_or_else(|| not_loaded(x, z))?;
+    Ok(normal.to_array().to_vec())
+}
+
+/// First terrain point hit by a ray as [x, y, z], or undefined on a miss within
+/// the view radius. `origin` and `dir` are [x, y, z] arrays. Uses the same
+/// heights as `get_terrain_height`, skipping terrain whose heights aren't
+/// available yet.
+#[wasm_bindgen]
+pub fn raycast_terrain(origin: &[f32], dir: &[f32]) -> Result<Option<Vec<f32>>, JsValue> {
+    let (Ok(origin), Ok(dir)) = (<[f32; 3]>::try_from(origin), <[f32; 3]>::try_from(dir)) else {
+        return Err(JsValue::from_str("origin and dir must have 3 components"));
+    };
+    let hit = with_app_state(|state| state.raycast_terrain(Vec3::from(origin), Vec3::from(dir)))?;
+    Ok(hit.map(|p| p.to_array().to_vec()))
+}
+
+/// Update fly camera motion settings from JavaScript
+#[wasm_bindgen]
+pub fn update_camera_settings(settings_js: JsValue) -> Result<(), JsValue> {
+    let settings: CameraSettings = serde_wasm_bindgen::from_value(settings_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse camera settings: {}", e)))?;
+    with_app_state_mut(|state| state.update_camera_settings(settings))
+}
+
+/// Get current fly camera motion settings as a JS object
+#[wasm_bindgen]
+pub fn get_camera_settings() -> Result<JsValue, JsValue> {
+    let settings = with_app_state(|state| state.get_camera_settings().clone())?;
+    serde_wasm_bindgen::to_value(&settings)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize camera settings: {}", e)))
+}
+
+/// Get default fly camera motion settings (before app initialization)
+#[wasm_bindgen]
+pub fn get_default_camera_settings() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&CameraSettings::default())
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize camera settings: {}", e)))
+}
+
+/// Minimum height the fly camera keeps above the terrain, off by default; 0 or less
+/// disables it. Checked against the same heights as `get_terrain_height`.
+#[wasm_bindgen]
+pub fn set_camera_ground_clearance(clearance: f32) -> Result<(), JsValue> {
+    with_app_state_mut(|state| state.set_camera_ground_clearance((clearance > 0.0).then_some(clearance)))
+}
+
+/// Switch between the fly camera and the orbit camera (also on C)
+#[wasm_bindgen]
+pub fn toggle_orbit_camera() -> Result<(), JsValue> {
+    with_app_state_mut(|state| state.toggle_orbit_camera())
+}
+
+/// Turntable rotation for the orbit camera, in radians per second
+#[wasm_bindgen]
+pub fn set_orbit_auto_rotate(enabled: bool, speed: f32) -> Result<(), JsValue> {
+    with_app_state_mut(|state| state.set_orbit_auto_rotate(enabled, speed))
+}
+
+/// Start recording the camera's route
+#[wasm_bindgen]
+pub fn start_camera_recording() -> Result<(), JsValue> {
+    with_app_state_mut(|state| state.start_camera_recording())
+}
+
+/// Stop recording and return the route as JSON
+#[wasm_bindgen]
+pub fn stop_camera_recording() -> Result<String, JsValue> {
+    let path = with_app_state_mut(|state| state.stop_camera_recording())?
+        .ok_or_else(|| JsValue::from_str("Not recording a camera path"))?;
+    path.to_json().map_err(|e| JsValue::from_str(&e))
+}
+
+/// Fly along a route recorded by `stop_camera_recording`
+#[wasm_bindgen]
+pub fn play_camera_path(path_json: &str) -> Result<(), JsValue> {
+    let path = CameraPath::from_json(path_json).map_err(|e| JsValue::from_str(&e))?;
+    with_app_state_mut(|state| state.play_camera_path(path))?.map_err(|e| JsValue::from_str(&e))
+}
+
+/// Stop camera path playback and hand control back to the user
+#[wasm_bindgen]
+pub fn stop_camera_playback() -> Result<(), JsValue> {
+    with_app_state_mut(|state| state.stop_camera_playback())
+}
+
+/// Whether a camera path is currently playing
+#[wasm_bindgen]
+pub fn is_camera_path_playing() -> Result<bool, JsValue> {
+    with_app_state(|state| state.is_playing_camera_path())
+}
+
+/// Regenerate terrain with current settings (called from JS on R key press)
+#[wasm_bindgen]
+pub fn regenerate_terrain() -> Result<(), JsValue> {
+    with_app_state_mut(|state| state.queue_terrain_regeneration())
+}
+
+/// Update sky settings from JavaScript
+#[wasm_bindgen]
+pub fn update_sky_settings(settings_js: JsValue) -> Result<(), JsValue> {
+    let settings: SkySettings = serde_wasm_bindgen::from_value(settings_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse sky settings: {}", e)))?;
+    with_app_state_mut(|state| state.update_sky_settings(settings))
+}
+
+/// Get current sky settings as a JS object
+#[wasm_bindgen]
+pub fn get_sky_settings() -> Result<JsValue, JsValue> {
+    let settings = with_app_state(|state| state.get_sky_settings().clone())?;
+    serde_wasm_bindgen::to_value(&settings)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize sky settings: {}", e)))
+}
+
+/// Jump to a time of day in hours (0-24, 12 is noon), enabling the day/night cycle
+#[wasm_bindgen]
+pub fn set_time_of_day(hours: f32) -> Result<(), JsValue> {
+    with_app_state_mut(|state| state.set_time_of_day(hours))
+}
+
+/// Update day/night cycle settings (enabled, time of day, time scale, paused) from JavaScript
+#[wasm_bindgen]
+pub fn update_day_cycle_settings(settings_js: JsValue) -> Result<(), JsValue> {
+    let settings: DayCycleSettings = serde_wasm_bindgen::from_value(settings_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse day cycle settings: {}", e)))?;
+    with_app_state_mut(|state| state.update_day_cycle_settings(settings))
+}
+
+/// Get current day/night cycle settings, including the current time of day
+#[wasm_bindgen]
+pub fn get_day_cycle_settings() -> Result<JsValue, JsValue> {
+    let settings = with_app_state(|state| state.get_day_cycle_settings().clone())?;
+    serde_wasm_bindgen::to_value(&settings)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize day cycle settings: {}", e)))
+}
+
+/// Update particle settings from JavaScript
+#[wasm_bindgen]
+pub fn update_particle_settings(settings_js: JsValue) -> Result<(), JsValue> {
+    let settings: ParticleSettings = serde_wasm_bindgen::from_value(settings_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse particle settings: {}", e)))?;
+    with_app_state_mut(|state| state.update_particle_settings(settings))
+}
+
+/// Get current particle settings as a JS object
+#[wasm_bindgen]
+pub fn get_particle_settings() -> Result<JsValue, JsValue> {
+    let settings = with_app_state(|state| state.get_particle_settings().clone())?;
+    serde_wasm_bindgen::to_value(&settings)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize particle settings: {}", e)))
+}
+
+/// Get default terrain settings (before app initialization)
+#[wasm_bindgen]
+pub fn get_default_terrain_settings() -> Result<JsValue, JsValue> {
+    default_settings_to_js(|p| p.terrain, "terrain defaults")
+}
+
+/// Get default sky settings (before app initialization)
+#[wasm_bindgen]
+pub fn get_default_sky_settings() -> Result<JsValue, JsValue> {
+    default_settings_to_js(|p| p.sky, "sky defaults")
+}
+
+/// Get default particle settings (before app initialization)
+#[wasm_bindgen]
+pub fn get_default_particle_settings() -> Result<JsValue, JsValue> {
+    default_settings_to_js(|p| p.particles, "particle defaults")
+}
+
+/// Get list of available presets (id, name)
+#[wasm_bindgen]
+pub fn get_preset_list() -> Result<JsValue, JsValue> {
+    let list = presets::get_preset_list();
+    serde_wasm_bindgen::to_value(&list)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize preset list: {}", e)))
+}
+
+/// Get a full preset by ID
+#[wasm_bindgen]
+pub fn get_preset(id: &str) -> Result<JsValue, JsValue> {
+    match presets::get_preset(id) {
+        Some(preset) => serde_wasm_bindgen::to_value(&preset)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize preset: {}", e))),
+        None => Err(JsValue::from_str(&format!("Unknown preset: {}", id))),
+    }
+}
+
+/// Get the default preset ID
+#[wasm_bindgen]
+pub fn get_default_preset_id() -> String {
+    presets::get_default_preset_id().to_string()
+}
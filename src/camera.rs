@@ -1,5 +1,6 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec3, Vec4};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
 
 use crate::input::InputState;
 
@@ -12,6 +13,101 @@ const ROTATION_SPEED: f32 = 0.8;
 /// Scroll wheel zoom speed multiplier
 const ZOOM_SPEED: f32 = 10.0;
 
+/// Range for scroll-adjusted fly speed, in units per second
+const MIN_MOVE_SPEED: f32 = 5.0;
+const MAX_MOVE_SPEED: f32 = 5000.0;
+
+/// Fraction of the fly speed changed per scroll unit
+const SCROLL_SPEED_STEP: f32 = 0.1;
+
+/// Velocity below which a damped camera is considered stopped
+const STOP_SPEED: f32 = 0.01;
+
+/// Orbit camera: closest and furthest distance from the target
+const MIN_ORBIT_DISTANCE: f32 = 20.0;
+const MAX_ORBIT_DISTANCE: f32 = 3000.0;
+
+/// Orbit camera: lowest elevation, keeping the camera above the target
+const MIN_ORBIT_PITCH: f32 = 0.05;
+
+/// Orbit camera: scroll dolly speed (fraction of the distance per scroll unit)
+const ORBIT_DOLLY_SPEED: f32 = 0.1;
+
+/// Walk mode: eye height above the ground
+const EYE_HEIGHT: f32 = 2.0;
+
+/// Walk mode: horizontal speed in units per second
+const WALK_SPEED: f32 = 25.0;
+
+/// Walk mode: downward acceleration in units per second squared
+const GRAVITY: f32 = 30.0;
+
+/// Walk mode: upward speed at the start of a jump
+const JUMP_SPEED: f32 = 12.0;
+
+/// Walk mode: steepest slope that can be walked up (~45 degrees)
+const MAX_WALK_SLOPE: f32 = 1.0;
+
+/// What the scroll wheel does in fly mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollAction {
+    /// Move along the view direction
+    Zoom,
+    /// Change `move_speed`
+    Speed,
+}
+
+/// Fly camera motion settings that can be modified at runtime
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub move_speed: f32,           // Units per second at full speed
+    pub look_sensitivity: f32,     // Radians per pixel of mouse movement
+    pub acceleration: f32,         // Multiples of the full speed gained per second; 0 starts instantly
+    pub damping: f32,              // Speed decay rate (per second) once keys are released; 0 stops instantly
+    pub sprint_multiplier: f32,    // Speed factor while F is held
+    pub precision_multiplier: f32, // Speed factor while X is held
+    pub scroll_action: ScrollAction,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            move_speed: 300.0,
+            look_sensitivity: 0.002,
+            acceleration: 6.0,
+            damping: 8.0,
+            sprint_multiplier: 4.0,
+            precision_multiplier: 0.25,
+            scroll_action: ScrollAction::Zoom,
+        }
+    }
+}
+
+impl CameraSettings {
+    /// Speed factor from the held sprint/precision keys
+    fn speed_multiplier(&self, input: &InputState) -> f32 {
+        let mut multiplier = 1.0;
+        if input.is_key_down("f") {
+            multiplier *= self.sprint_multiplier;
+        }
+        if input.is_key_down("x") {
+            multiplier *= self.precision_multiplier;
+        }
+        multiplier
+    }
+}
+
+/// How the camera moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Free flight, passing through the terrain
+    Fly,
+    /// Walk on the terrain surface with gravity and jumping
+    Walk,
+}
+
 /// Fly camera for exploring the terrain
 pub struct FlyCamera {
     pub position: Vec3,
@@ -23,8 +119,13 @@ pub struct FlyCamera {
     pub near: f32,
     pub far: f32,
 
-    pub move_speed: f32,
-    pub look_sensitivity: f32,
+    pub settings: CameraSettings,
+
+    pub mode: CameraMode,
+    pub ground_clearance: Option<f32>, // Fly mode: minimum height above the terrain; None (the default) to pass through
+    velocity: Vec3,         // Fly mode only
+    vertical_velocity: f32, // Walk mode only
+    grounded: bool,
 }
 
 impl FlyCamera {
@@ -39,8 +140,13 @@ impl FlyCamera {
             near: 0.1,
             far: 5000.0,
 
-            move_speed: 300.0,
-            look_sensitivity: 0.002,
+            settings: CameraSettings::default(),
+
+            mode: CameraMode::Fly,
+            ground_clearance: None,
+            velocity: Vec3::ZERO,
+            vertical_velocity: 0.0,
+            grounded: false,
         }
     }
 
@@ -54,15 +160,27 @@ impl FlyCamera {
     }
 
     /// Normalized forward direction
-    fn forward_direction(&self) -> Vec3 {
+    pub fn forward_direction(&self) -> Vec3 {
         self.forward_vector().normalize()
     }
 
-    pub fn update(&mut self, input: &InputState, dt: f32) {
+    /// Switch between flying and walking. Walking starts by falling to the ground.
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Fly => CameraMode::Walk,
+            CameraMode::Walk => CameraMode::Fly,
+        };
+        self.velocity = Vec3::ZERO;
+        self.vertical_velocity = 0.0;
+        self.grounded = false;
+        log::info!("Camera mode: {:?}", self.mode);
+    }
+
+    fn update_look(&mut self, input: &InputState, dt: f32) {
         // Mouse look (only when locked)
         if input.mouse_locked {
-            self.yaw -= input.mouse_delta_x * self.look_sensitivity;
-            self.pitch -= input.mouse_delta_y * self.look_sensitivity;
+            self.yaw -= input.mouse_delta_x * self.settings.look_sensitivity;
+            self.pitch -= input.mouse_delta_y * self.settings.look_sensitivity;
             // Clamp pitch to prevent flipping
             self.pitch = self.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
         }
@@ -74,105 +192,411 @@ impl FlyCamera {
         if input.is_key_down("e") || input.is_key_down("o") {
             self.yaw -= ROTATION_SPEED * dt;
         }
+    }
 
+    fn update_fly(&mut self, input: &InputState, dt: f32, height_at: &dyn Fn(f32, f32) -> Option<f32>) {
         // Calculate movement vectors
         let forward = self.forward_direction();
 
         let right = Vec3::new(-self.yaw.cos(), 0.0, self.yaw.sin()).normalize();
         let up = Vec3::Y;
 
-        // Scroll zoom (move along forward direction)
+        // Scroll zooms (moves along the forward direction) or changes speed
         if input.scroll_delta.abs() > 0.001 {
-            let zoom_amount = -input.scroll_delta * ZOOM_SPEED;
-            self.position += forward * zoom_amount;
+            match self.settings.scroll_action {
+                ScrollAction::Zoom => {
+                    let zoom_amount = -input.scroll_delta * ZOOM_SPEED;
+                    self.position += forward * zoom_amount;
+                }
+                ScrollAction::Speed => {
+                    let speed = self.settings.move_speed * (-input.scroll_delta * SCROLL_SPEED_STEP).exp();
+                    self.settings.move_speed = speed.clamp(MIN_MOVE_SPEED, MAX_MOVE_SPEED);
+                }
+            }
         }
 
         // Movement input (WASD or IJKL)
-        let mut velocity = Vec3::ZERO;
+        let mut direction = Vec3::ZERO;
 
         if input.is_key_down("w") || input.is_key_down("i") {
-            velocity += forward;
+            direction += forward;
         }
         if input.is_key_down("s") || input.is_key_down("k") {
-            velocity -= forward;
+            direction -= forward;
         }
         if input.is_key_down("a") || input.is_key_down("j") {
-            velocity -= right;
+            direction -= right;
         }
         if input.is_key_down("d") || input.is_key_down("l") {
-            velocity += right;
+            direction += right;
         }
         if input.is_key_down(" ") {
             // Space - up
-            velocity += up;
+            direction += up;
         }
         if input.is_key_down("shift") {
             // Shift - down
-            velocity -= up;
+            direction -= up;
+        }
+
+        // Accelerate towards the target velocity while keys are held, coast to a stop otherwise
+        let settings = &self.settings;
+        let speed = settings.move_speed * settings.speed_multiplier(input);
+        if direction.length_squared() > 0.0 {
+            let target = direction.normalize() * speed;
+            self.velocity = if settings.acceleration > 0.0 {
+                let max_change = settings.acceleration * speed * dt;
+                self.velocity + (target - self.velocity).clamp_length_max(max_change)
+            } else {
+                target
+            };
+        } else if settings.damping > 0.0 {
+            self.velocity *= (-settings.damping * dt).exp();
+        } else {
+            self.velocity = Vec3::ZERO;
         }
+        if self.velocity.length() < STOP_SPEED {
+            self.velocity = Vec3::ZERO;
+        }
+        self.position += self.velocity * dt;
 
-        // Apply movement
-        if velocity.length_squared() > 0.0 {
-            velocity = velocity.normalize() * self.move_speed * dt;
-            self.position += velocity;
+        // Keep zoom and movement from ending up inside the terrain
+        if let (Some(clearance), Some(ground)) = (self.ground_clearance, height_at(self.position.x, self.position.z)) {
+            let min_y = ground + clearance;
+            if self.position.y < min_y {
+                self.position.y = min_y;
+                self.velocity.y = self.velocity.y.max(0.0);
+            }
         }
     }
 
-    pub fn view_matrix(&self) -> Mat4 {
+    fn update_walk(&mut self, input: &InputState, dt: f32, height_at: &dyn Fn(f32, f32) -> Option<f32>) {
+        // Movement stays horizontal regardless of pitch
+        let forward = Vec3::new(self.yaw.sin(), 0.0, self.yaw.cos());
+        let right = Vec3::new(-self.yaw.cos(), 0.0, self.yaw.sin());
+
+        let mut direction = Vec3::ZERO;
+        if input.is_key_down("w") || input.is_key_down("i") {
+            direction += forward;
+        }
+        if input.is_key_down("s") || input.is_key_down("k") {
+            direction -= forward;
+        }
+        if input.is_key_down("a") || input.is_key_down("j") {
+            direction -= right;
+        }
+        if input.is_key_down("d") || input.is_key_down("l") {
+            direction += right;
+        }
+
+        let speed = WALK_SPEED * self.settings.speed_multiplier(input);
+        let feet = self.position.y - EYE_HEIGHT;
+        if direction.length_squared() > 0.0 {
+            let step = direction.normalize() * speed * dt;
+            // Slopes too steep to climb block the move; try each axis alone to slide along them
+            for candidate in [step, Vec3::new(step.x, 0.0, 0.0), Vec3::new(0.0, 0.0, step.z)] {
+                // Terrain that isn't known yet blocks the move too
+                let target = self.position + candidate;
+                let Some(rise) = height_at(target.x, target.z).map(|h| h - feet) else {
+                    continue;
+                };
+                if rise <= candidate.length() * MAX_WALK_SLOPE {
+                    self.position = target;
+                    break;
+                }
+            }
+        }
+
+        // Hold still until the ground underneath is known
+        let Some(ground) = height_at(self.position.x, self.position.z).map(|h| h + EYE_HEIGHT) else {
+            return;
+        };
+        if self.grounded && input.is_key_down(" ") {
+            self.vertical_velocity = JUMP_SPEED;
+            self.grounded = false;
+        }
+
+        // Stay on the ground walking downhill, as long as the slope is walkable
+        let max_drop = speed * dt * MAX_WALK_SLOPE;
+        if self.grounded && self.position.y - ground <= max_drop {
+            self.position.y = ground;
+            return;
+        }
+
+        self.vertical_velocity -= GRAVITY * dt;
+        self.position.y += self.vertical_velocity * dt;
+        self.grounded = self.position.y <= ground;
+        if self.grounded {
+            self.position.y = ground;
+            self.vertical_velocity = 0.0;
+        }
+    }
+
+    /// Point the camera from its position towards `target`
+    pub fn look_at(&mut self, target: Vec3) {
+        let to_target = (target - self.position).normalize_or_zero();
+        if to_target == Vec3::ZERO {
+            return;
+        }
+        self.yaw = to_target.x.atan2(to_target.z);
+        self.pitch = to_target.y.asin().clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+}
+
+impl CameraController for FlyCamera {
+    /// Uses `height_at` for walking and the fly mode ground clearance
+    fn update(&mut self, input: &InputState, dt: f32, height_at: &dyn Fn(f32, f32) -> Option<f32>) {
+        self.update_look(input, dt);
+        match self.mode {
+            CameraMode::Fly => self.update_fly(input, dt, height_at),
+            CameraMode::Walk => self.update_walk(input, dt, height_at),
+        }
+    }
+
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn view_matrix(&self) -> Mat4 {
         let forward = self.forward_direction();
         let target = self.position + forward;
         Mat4::look_at_rh(self.position, target, Vec3::Y)
     }
 
-    pub fn projection_matrix(&self) -> Mat4 {
+    fn projection_matrix(&self) -> Mat4 {
         Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
     }
 
-    pub fn view_projection_matrix(&self) -> Mat4 {
+    fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+}
+
+/// Turntable camera circling a target point on the terrain
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,   // Direction from the target to the camera (radians)
+    pub pitch: f32, // Elevation above the target (radians)
+
+    pub aspect: f32,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+
+    pub look_sensitivity: f32,
+    pub auto_rotate: bool,
+    pub auto_rotate_speed: f32, // Radians per second
+}
+
+impl OrbitCamera {
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            target: Vec3::ZERO,
+            distance: 600.0,
+            yaw: 0.0,
+            pitch: 0.5,
+
+            aspect,
+            fov: 70.0_f32.to_radians(),
+            near: 0.1,
+            far: 5000.0,
+
+            look_sensitivity: 0.005,
+            auto_rotate: false,
+            auto_rotate_speed: 0.2,
+        }
+    }
+
+    /// Orbit `target`, starting from the camera position `eye`
+    pub fn look_at_from(&mut self, target: Vec3, eye: Vec3) {
+        let offset = eye - target;
+        self.target = target;
+        self.distance = offset.length().clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+        self.yaw = offset.x.atan2(offset.z);
+        let horizontal = Vec2::new(offset.x, offset.z).length();
+        self.pitch = offset.y.atan2(horizontal).clamp(MIN_ORBIT_PITCH, PITCH_LIMIT);
+    }
+
+    /// Camera position on the orbit
+    pub fn eye(&self) -> Vec3 {
+        let direction = Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        );
+        self.target + direction * self.distance
+    }
+}
+
+impl CameraController for OrbitCamera {
+    fn update(&mut self, input: &InputState, dt: f32, height_at: &dyn Fn(f32, f32) -> Option<f32>) {
+        // Drag to rotate
+        if input.mouse_down {
+            self.yaw -= input.mouse_delta_x * self.look_sensitivity;
+            self.pitch += input.mouse_delta_y * self.look_sensitivity;
+        }
+        if input.is_key_down("q") || input.is_key_down("u") {
+            self.yaw += ROTATION_SPEED * dt;
+        }
+        if input.is_key_down("e") || input.is_key_down("o") {
+            self.yaw -= ROTATION_SPEED * dt;
+        }
+        if self.auto_rotate {
+            self.yaw += self.auto_rotate_speed * dt;
+        }
+        self.pitch = self.pitch.clamp(MIN_ORBIT_PITCH, PITCH_LIMIT);
+
+        // Scroll to dolly, proportionally to the current distance
+        if input.scroll_delta.abs() > 0.001 {
+            self.distance *= (input.scroll_delta * ORBIT_DOLLY_SPEED).exp();
+            self.distance = self.distance.clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+        }
+
+        // Keep the target on the terrain surface, where it is known
+        if let Some(ground) = height_at(self.target.x, self.target.z) {
+            self.target.y = ground;
+        }
+    }
+
+    fn position(&self) -> Vec3 {
+        self.eye()
+    }
+
+    fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye(), self.target, Vec3::Y)
+    }
+
+    fn projection_matrix(&self) -> Mat4 {
+        Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
+    }
+
+    fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+}
+
+/// Which controller `CameraRig` is using
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControllerKind {
+    Fly,
+    Orbit,
+}
+
+/// The available camera controllers. Each keeps its state while another is active.
+pub struct CameraRig {
+    pub fly: FlyCamera,
+    pub orbit: OrbitCamera,
+    active: ControllerKind,
+}
+
+impl CameraRig {
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            fly: FlyCamera::new(aspect),
+            orbit: OrbitCamera::new(aspect),
+            active: ControllerKind::Fly,
+        }
+    }
+
+    pub fn active_kind(&self) -> ControllerKind {
+        self.active
+    }
+
+    pub fn active(&self) -> &dyn CameraController {
+        match self.active {
+            ControllerKind::Fly => &self.fly,
+            ControllerKind::Orbit => &self.orbit,
+        }
+    }
+
+    pub fn active_mut(&mut self) -> &mut dyn CameraController {
+        match self.active {
+            ControllerKind::Fly => &mut self.fly,
+            ControllerKind::Orbit => &mut self.orbit,
+        }
+    }
+
+    /// Start orbiting `target` from wherever the fly camera is
+    pub fn orbit_around(&mut self, target: Vec3) {
+        self.orbit.look_at_from(target, self.fly.position);
+        self.active = ControllerKind::Orbit;
+    }
+
+    /// Go back to the fly camera, continuing from the orbit camera's view
+    pub fn fly_from_orbit(&mut self) {
+        if self.active == ControllerKind::Orbit {
+            self.fly.position = self.orbit.eye();
+            self.fly.look_at(self.orbit.target);
+            self.active = ControllerKind::Fly;
+        }
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.fly.set_aspect(aspect);
+        self.orbit.set_aspect(aspect);
+    }
+}
+
+/// Turns input into a view. Rendering and streaming only use this trait,
+/// so controllers can be swapped at runtime.
+pub trait CameraController {
+    /// Move the camera for one frame. `height_at` gives the terrain height at a world
+    /// (x, z) position, or None where it isn't known yet.
+    fn update(&mut self, input: &InputState, dt: f32, height_at: &dyn Fn(f32, f32) -> Option<f32>);
+    fn position(&self) -> Vec3;
+    fn view_matrix(&self) -> Mat4;
+    fn projection_matrix(&self) -> Mat4;
+    fn set_aspect(&mut self, aspect: f32);
+
+    fn view_projection_matrix(&self) -> Mat4 {
         self.projection_matrix() * self.view_matrix()
     }
 
-    pub fn uniform_data(&self) -> CameraUniform {
+    fn uniform_data(&self) -> CameraUniform {
         CameraUniform {
             view_proj: self.view_projection_matrix().to_cols_array_2d(),
-            camera_pos: self.position.to_array(),
+            camera_pos: self.position().to_array(),
             _padding: 0.0,
         }
     }
+    /// Frustum planes of the view-projection matrix, see `frustum_planes`
+    fn extract_frustum_planes(&self) -> [Vec4; 6] {
+        frustum_planes(self.view_projection_matrix())
+    }
+}
 
-    /// Extract frustum planes from the view-projection matrix
-    /// Returns 6 planes: [left, right, bottom, top, near, far]
-    /// Each plane is (nx, ny, nz, d) where nx*x + ny*y + nz*z + d >= 0 means inside
-    pub fn extract_frustum_planes(&self) -> [Vec4; 6] {
-        let vp = self.view_projection_matrix();
-        let cols = vp.to_cols_array_2d();
-
-        // Extract rows from the transposed matrix for plane extraction
-        let row0 = Vec4::new(cols[0][0], cols[1][0], cols[2][0], cols[3][0]);
-        let row1 = Vec4::new(cols[0][1], cols[1][1], cols[2][1], cols[3][1]);
-        let row2 = Vec4::new(cols[0][2], cols[1][2], cols[2][2], cols[3][2]);
-        let row3 = Vec4::new(cols[0][3], cols[1][3], cols[2][3], cols[3][3]);
-
-        // Extract and normalize planes
-        let mut planes = [
-            row3 + row0, // Left
-            row3 - row0, // Right
-            row3 + row1, // Bottom
-            row3 - row1, // Top
-            row3 + row2, // Near
-            row3 - row2, // Far
-        ];
-
-        // Normalize each plane
-        for plane in &mut planes {
-            let len = (plane.x * plane.x + plane.y * plane.y + plane.z * plane.z).sqrt();
-            if len > 0.0 {
-                *plane /= len;
-            }
-        }
+/// Extract frustum planes from a view-projection matrix
+/// Returns 6 planes: [left, right, bottom, top, near, far]
+/// Each plane is (nx, ny, nz, d) where nx*x + ny*y + nz*z + d >= 0 means inside
+pub fn frustum_planes(vp: Mat4) -> [Vec4; 6] {
+    let cols = vp.to_cols_array_2d();
+
+    // Extract rows from the transposed matrix for plane extraction
+    let row0 = Vec4::new(cols[0][0], cols[1][0], cols[2][0], cols[3][0]);
+    let row1 = Vec4::new(cols[0][1], cols[1][1], cols[2][1], cols[3][1]);
+    let row2 = Vec4::new(cols[0][2], cols[1][2], cols[2][2], cols[3][2]);
+    let row3 = Vec4::new(cols[0][3], cols[1][3], cols[2][3], cols[3][3]);
 
-        planes
+    // Extract and normalize planes
+    let mut planes = [
+        row3 + row0, // Left
+        row3 - row0, // Right
+        row3 + row1, // Bottom
+        row3 - row1, // Top
+        row3 + row2, // Near
+        row3 - row2, // Far
+    ];
+
+    // Normalize each plane
+    for plane in &mut planes {
+        let len = (plane.x * plane.x + plane.y * plane.y + plane.z * plane.z).sqrt();
+        if len > 0.0 {
+            *plane /= len;
+        }
     }
+
+    planes
 }
 
 /// Camera uniform data for GPU
@@ -183,3 +607,148 @@ pub struct CameraUniform {
     pub camera_pos: [f32; 3],
     pub _padding: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_mode_follows_ground_and_stops_at_cliffs() {
+        let mut camera = FlyCamera::new(1.0);
+        camera.toggle_mode();
+        let mut input = InputState::new();
+
+        // Flat ground at 10, with a cliff rising to 60 past z = 20
+        let height_at = |_x: f32, z: f32| Some(if z > 20.0 { 60.0 } else { 10.0 });
+        for _ in 0..300 {
+            camera.update(&input, 1.0 / 60.0, &height_at);
+        }
+        assert_eq!(camera.position.y, 10.0 + EYE_HEIGHT);
+
+        input.keys.insert("w".to_string());
+        for _ in 0..120 {
+            camera.update(&input, 1.0 / 60.0, &height_at);
+        }
+        assert!(camera.position.z <= 20.0, "walked up the cliff to {}", camera.position);
+        assert_eq!(camera.position.y, 10.0 + EYE_HEIGHT);
+
+        // A jump leaves the ground and lands again
+        input.keys.insert(" ".to_string());
+        camera.update(&input, 1.0 / 60.0, &height_at);
+        assert!(camera.position.y > 10.0 + EYE_HEIGHT);
+        input.keys.clear();
+        for _ in 0..120 {
+            camera.update(&input, 1.0 / 60.0, &height_at);
+        }
+        assert_eq!(camera.position.y, 10.0 + EYE_HEIGHT);
+    }
+
+    #[test]
+    fn walk_mode_waits_for_unknown_ground() {
+        let mut camera = FlyCamera::new(1.0);
+        camera.toggle_mode();
+        let mut input = InputState::new();
+        input.keys.insert("w".to_string());
+
+        // Nothing known yet: no falling and no walking
+        let start = camera.position;
+        camera.update(&input, 1.0 / 60.0, &|_, _| None);
+        assert_eq!(camera.position, start);
+
+        // Ground known only behind the edge at z = 1 blocks the way forward
+        let height_at = |_x: f32, z: f32| (z < 1.0).then_some(10.0);
+        for _ in 0..300 {
+            camera.update(&input, 1.0 / 60.0, &height_at);
+        }
+        assert!(camera.position.z < 1.0, "walked onto unknown ground at {}", camera.position);
+        assert_eq!(camera.position.y, 10.0 + EYE_HEIGHT);
+    }
+
+    #[test]
+    fn fly_mode_keeps_ground_clearance() {
+        let mut camera = FlyCamera::new(1.0);
+        camera.position = Vec3::new(0.0, 20.0, 0.0);
+        camera.pitch = -1.0;
+        let mut input = InputState::new();
+        let height_at = |_x: f32, _z: f32| Some(10.0);
+        camera.ground_clearance = Some(5.0);
+
+        // Zooming in towards the ground stops at the clearance
+        input.scroll_delta = -50.0;
+        camera.update(&input, 1.0 / 60.0, &height_at);
+        assert_eq!(camera.position.y, 15.0);
+
+        input.scroll_delta = 0.0;
+        input.keys.insert("shift".to_string());
+        camera.ground_clearance = None;
+        camera.update(&input, 1.0, &height_at);
+        assert!(camera.position.y < 10.0);
+    }
+
+    #[test]
+    fn switching_controllers_keeps_the_view() {
+        let mut rig = CameraRig::new(1.5);
+        rig.fly.position = Vec3::new(100.0, 250.0, -80.0);
+        let target = Vec3::new(400.0, 30.0, 200.0);
+        rig.fly.look_at(target);
+        let fly_view = rig.active().view_projection_matrix();
+
+        rig.orbit_around(target);
+        assert_eq!(rig.active_kind(), ControllerKind::Orbit);
+        assert!(rig.active().position().distance(rig.fly.position) < 1e-2);
+        assert!(rig.active().view_projection_matrix().abs_diff_eq(fly_view, 1e-3));
+
+        // Auto-rotate turns the orbit around a target that stays on the ground
+        rig.orbit.auto_rotate = true;
+        rig.active_mut().update(&InputState::new(), 1.0, &|_, _| Some(30.0));
+        assert!(rig.orbit.yaw != 0.0 && rig.orbit.target == target);
+
+        rig.fly_from_orbit();
+        assert_eq!(rig.active_kind(), ControllerKind::Fly);
+        assert!(rig.fly.position.distance(rig.orbit.eye()) < 1e-3);
+    }
+
+    #[test]
+    fn fly_speed_ramps_up_and_coasts_to_a_stop() {
+        let mut camera = FlyCamera::new(1.0);
+        camera.position = Vec3::new(0.0, 500.0, 0.0);
+        let mut input = InputState::new();
+        let flat = |_x: f32, _z: f32| Some(0.0);
+        let dt = 1.0 / 60.0;
+
+        input.keys.insert("w".to_string());
+        camera.update(&input, dt, &flat);
+        let first_step = camera.position.z;
+        assert!(first_step > 0.0 && first_step < camera.settings.move_speed * dt);
+        for _ in 0..60 {
+            camera.update(&input, dt, &flat);
+        }
+        assert!((camera.velocity.length() - camera.settings.move_speed).abs() < 1e-2);
+
+        // Sprinting raises the target speed
+        input.keys.insert("f".to_string());
+        for _ in 0..60 {
+            camera.update(&input, dt, &flat);
+        }
+        let sprint = camera.settings.move_speed * camera.settings.sprint_multiplier;
+        assert!((camera.velocity.length() - sprint).abs() < 1e-1);
+
+        // Releasing the keys coasts, then stops
+        input.keys.clear();
+        let before = camera.position;
+        camera.update(&input, dt, &flat);
+        assert!(camera.position.z > before.z);
+        for _ in 0..300 {
+            camera.update(&input, dt, &flat);
+        }
+        assert_eq!(camera.velocity, Vec3::ZERO);
+
+        // Scroll can adjust speed instead of zooming
+        camera.settings.scroll_action = ScrollAction::Speed;
+        input.scroll_delta = -5.0;
+        let position = camera.position;
+        camera.update(&input, dt, &flat);
+        assert_eq!(camera.position, position);
+        assert!(camera.settings.move_speed > CameraSettings::default().move_speed);
+    }
+}
@@ -0,0 +1,183 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::FlyCamera;
+
+/// Minimum time between recorded keyframes, in seconds
+const RECORD_INTERVAL: f32 = 0.1;
+
+/// A fly camera pose at a point in time
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PathKeyframe {
+    pub time: f32, // Seconds from the start of the path
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub fov: f32, // Radians
+}
+
+impl PathKeyframe {
+    pub fn from_camera(time: f32, camera: &FlyCamera) -> Self {
+        Self {
+            time,
+            position: camera.position.to_array(),
+            yaw: camera.yaw,
+            pitch: camera.pitch,
+            fov: camera.fov,
+        }
+    }
+
+    /// Move the camera to this pose
+    pub fn apply(&self, camera: &mut FlyCamera) {
+        camera.position = Vec3::from(self.position);
+        camera.yaw = self.yaw;
+        camera.pitch = self.pitch;
+        camera.fov = self.fov;
+    }
+}
+
+/// Recorded camera route, played back along a Catmull-Rom spline through the keyframes
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<PathKeyframe>,
+}
+
+impl CameraPath {
+    /// Append the camera's pose, skipping it if the last keyframe is too recent
+    pub fn record(&mut self, time: f32, camera: &FlyCamera) {
+        if let Some(last) = self.keyframes.last() {
+            if time - last.time < RECORD_INTERVAL {
+                return;
+            }
+        }
+        self.keyframes.push(PathKeyframe::from_camera(time, camera));
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Interpolated pose at `time`, clamped to the ends of the path.
+    /// None if the path is empty.
+    pub fn sample(&self, time: f32) -> Option<PathKeyframe> {
+        let keys = &self.keyframes;
+        let first = keys.first()?;
+        let last = keys.last()?;
+        if time <= first.time {
+            return Some(*first);
+        }
+        if time >= last.time {
+            return Some(*last);
+        }
+
+        // Segment k1..k2 containing `time`, with its outer neighbours (clamped at the ends)
+        let i = keys.partition_point(|k| k.time <= time) - 1;
+        let k0 = &keys[i.saturating_sub(1)];
+        let k1 = &keys[i];
+        let k2 = &keys[i + 1];
+        let k3 = &keys[(i + 2).min(keys.len() - 1)];
+        let t = (time - k1.time) / (k2.time - k1.time);
+
+        let spline = |get: fn(&PathKeyframe) -> f32| catmull_rom([k0, k1, k2, k3].map(|k| (k.time, get(k))), t);
+        Some(PathKeyframe {
+            time,
+            position: [
+                spline(|k| k.position[0]),
+                spline(|k| k.position[1]),
+                spline(|k| k.position[2]),
+            ],
+            yaw: spline(|k| k.yaw),
+            pitch: spline(|k| k.pitch),
+            fov: spline(|k| k.fov),
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("Failed to serialize camera path: {}", e))
+    }
+
+    /// Parse a path, rejecting keyframes whose times don't increase
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let path: Self = serde_json::from_str(json).map_err(|e| format!("Failed to parse camera path: {}", e))?;
+        if path.keyframes.windows(2).any(|pair| pair[1].time <= pair[0].time) {
+            return Err("Camera path keyframe times must be strictly increasing".to_string());
+        }
+        Ok(path)
+    }
+}
+
+/// Catmull-Rom spline through (time, value) points p1..p2, at `t` in [0, 1] between them.
+/// Tangents use the actual keyframe spacing, so uneven recording intervals don't overshoot.
+fn catmull_rom(points: [(f32, f32); 4], t: f32) -> f32 {
+    let [(t0, p0), (t1, p1), (t2, p2), (t3, p3)] = points;
+    let span = t2 - t1;
+    let tangent = |ta: f32, pa: f32, tb: f32, pb: f32| {
+        if tb > ta {
+            (pb - pa) / (tb - ta) * span
+        } else {
+            0.0
+        }
+    };
+    let m1 = tangent(t0, p0, t2, p2);
+    let m2 = tangent(t1, p1, t3, p3);
+
+    // Cubic Hermite basis
+    let tt = t * t;
+    let ttt = tt * t;
+    (2.0 * ttt - 3.0 * tt + 1.0) * p1 + (ttt - 2.0 * tt + t) * m1 + (-2.0 * ttt + 3.0 * tt) * p2 + (ttt - tt) * m2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, x: f32) -> PathKeyframe {
+        PathKeyframe {
+            time,
+            position: [x, 100.0, 2.0 * x],
+            yaw: x * 0.01,
+            pitch: -0.2,
+            fov: 1.2,
+        }
+    }
+
+    #[test]
+    fn playback_passes_through_keyframes_and_round_trips() {
+        let path = CameraPath {
+            keyframes: vec![keyframe(0.0, 0.0), keyframe(1.0, 10.0), keyframe(2.5, 40.0), keyframe(3.0, 45.0)],
+        };
+        for key in &path.keyframes {
+            let sampled = path.sample(key.time).unwrap();
+            assert!((Vec3::from(sampled.position) - Vec3::from(key.position)).length() < 1e-4);
+        }
+        assert_eq!(path.sample(-1.0), path.keyframes.first().copied());
+        assert_eq!(path.sample(10.0), path.keyframes.last().copied());
+
+        // Between keyframes the route stays between its neighbours
+        let mid = path.sample(1.75).unwrap();
+        assert!(mid.position[0] > 10.0 && mid.position[0] < 40.0);
+        assert_eq!(mid.pitch, -0.2);
+
+        let json = path.to_json().unwrap();
+        assert_eq!(CameraPath::from_json(&json).unwrap(), path);
+
+        let backwards = CameraPath {
+            keyframes: vec![keyframe(1.0, 0.0), keyframe(0.5, 1.0)],
+        };
+        assert!(CameraPath::from_json(&backwards.to_json().unwrap()).is_err());
+    }
+
+    #[test]
+    fn recording_respects_the_interval() {
+        let mut camera = FlyCamera::new(1.0);
+        let mut path = CameraPath::default();
+        for frame in 0..60 {
+            camera.position.x = frame as f32;
+            path.record(frame as f32 / 64.0, &camera);
+        }
+        // 7 frames is the first step of at least 0.1s
+        assert_eq!(path.keyframes.len(), 9);
+        assert_eq!(path.keyframes[1].position[0], 7.0);
+    }
+}
@@ -0,0 +1,388 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Vec3, Vec4};
+use wgpu::util::DrawIndexedIndirectArgs;
+use wgpu::*;
+
+use crate::terrain::{TerrainQuality, LOD_DISTANCES};
+
+const CULL_WORKGROUP_SIZE: u32 = 64; // Must match @workgroup_size in shader
+const DRAW_ARGS_BYTES: u64 = std::mem::size_of::<DrawIndexedIndirectArgs>() as u64;
+
+/// Per-slot render data, shared by the culling pass and the terrain vertex shader
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(crate) struct ChunkInfo {
+    pub world_offset: [f32; 2],
+    pub height_offset: u32, // First sample of the chunk in the shared height buffer
+    pub ready: u32,
+    pub min_height: f32, // Written on the GPU by reduce_height_bounds
+    pub max_height: f32,
+    pub ao_offset: u32, // First value of the chunk in the shared AO buffer
+    pub _padding: u32,
+}
+
+/// Culling shader parameters - must match shader layout
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct CullParams {
+    planes: [[f32; 4]; 6],
+    camera_pos: [f32; 4],
+    lod_distances: [f32; 4],
+    skirt_depth: f32,
+    slot_count: u32,
+    lod_count: u32,
+    visible_stride: u32,
+}
+
+/// Index range of one level of detail in the shared index buffer
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LodRange {
+    pub first_index: u32,
+    pub index_count: u32,
+}
+
+/// GPU frustum culling for terrain chunks. A compute pass tests every slot and
+/// writes one `DrawIndexedIndirect` block per level of detail, with the visible
+/// slots listed per level for the vertex shader to look up by instance index.
+pub struct ChunkCuller {
+    pipeline: ComputePipeline,
+    bind_group: BindGroup,
+    params_buffer: Buffer,
+    chunk_buffer: Buffer,
+    draw_buffer: Buffer,
+    visible_buffer: Buffer,
+
+    // Draw arguments with no instances, copied in before each cull
+    empty_draws: Vec<u8>,
+    skirt_depth: f32,
+    slot_count: u32,
+    lod_count: u32,
+    visible_stride: u32,
+    multi_draw: bool,
+}
+
+impl ChunkCuller {
+    pub(crate) fn new(device: &Device, quality: &TerrainQuality, lods: &[LodRange]) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Culling Shader"),
+            source: ShaderSource::Wgsl(include_str!("../shaders/culling.wgsl").into()),
+        });
+
+        let slot_count = quality.max_chunks() as u32;
+        let lod_count = lods.len() as u32;
+        let skirt_depth = quality.lod_strides().last().copied().unwrap_or(1) as f32 * quality.sample_spacing();
+
+        // Each level's list starts on a storage offset boundary, so it can be bound on its own
+        let alignment = device.limits().min_storage_buffer_offset_alignment / 4;
+        let visible_stride = slot_count.next_multiple_of(alignment);
+
+        // With multi-draw, all levels are drawn in one call and first_instance selects the
+        // list. Otherwise each level is drawn separately with its own list bound.
+        let multi_draw = device
+            .features()
+            .contains(Features::MULTI_DRAW_INDIRECT | Features::INDIRECT_FIRST_INSTANCE);
+        let empty_draws: Vec<u8> = lods
+            .iter()
+            .enumerate()
+            .flat_map(|(lod, range)| {
+                DrawIndexedIndirectArgs {
+                    index_count: range.index_count,
+                    instance_count: 0,
+                    first_index: range.first_index,
+                    base_vertex: 0,
+                    first_instance: if multi_draw { lod as u32 * visible_stride } else { 0 },
+                }
+                .as_bytes()
+                .to_vec()
+            })
+            .collect();
+
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Cull Params Buffer"),
+            size: std::mem::size_of::<CullParams>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let chunk_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Chunk Info Buffer"),
+            size: slot_count as u64 * std::mem::size_of::<ChunkInfo>() as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let draw_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Terrain Draw Buffer"),
+            size: lod_count as u64 * DRAW_ARGS_BYTES,
+            usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let visible_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Visible Chunks Buffer"),
+            size: (lod_count * visible_stride) as u64 * 4,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding: u32, read_only: bool| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Cull Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, false),
+                storage_entry(3, false),
+            ],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Cull Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: chunk_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: draw_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: visible_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Cull Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader_constants = quality.shader_constants();
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Chunk Cull Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("cull_chunks"),
+            compilation_options: PipelineCompilationOptions {
+                constants: &shader_constants,
+                ..Default::default()
+            },
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            params_buffer,
+            chunk_buffer,
+            draw_buffer,
+            visible_buffer,
+            empty_draws,
+            skirt_depth,
+            slot_count,
+            lod_count,
+            visible_stride,
+            multi_draw,
+        }
+    }
+
+    /// Whether every level is drawn by a single multi-draw-indirect call
+    pub fn multi_draw(&self) -> bool {
+        self.multi_draw
+    }
+
+    pub fn lod_count(&self) -> u32 {
+        self.lod_count
+    }
+
+    /// Indirect arguments, one `DrawIndexedIndirectArgs` per level of detail
+    pub fn draw_buffer(&self) -> &Buffer {
+        &self.draw_buffer
+    }
+
+    pub(crate) fn chunk_buffer(&self) -> &Buffer {
+        &self.chunk_buffer
+    }
+
+    /// Visible slot list for the vertex shader: every level for multi-draw, or one level
+    pub(crate) fn visible_binding(&self, lod: Option<u32>) -> BufferBinding<'_> {
+        match lod {
+            Some(lod) => BufferBinding {
+                buffer: &self.visible_buffer,
+                offset: (lod * self.visible_stride) as u64 * 4,
+                size: BufferSize::new(self.visible_stride as u64 * 4),
+            },
+            None => self.visible_buffer.as_entire_buffer_binding(),
+        }
+    }
+
+    /// Update the render data for one slot. The height bounds are filled in on the GPU
+    /// by `reduce_height_bounds` after the chunk is generated.
+    pub(crate) fn write_chunk(&self, queue: &Queue, slot: usize, info: ChunkInfo) {
+        let offset = (slot * std::mem::size_of::<ChunkInfo>()) as u64;
+        queue.write_buffer(&self.chunk_buffer, offset, bytemuck::cast_slice(&[info]));
+    }
+
+    /// Reset the draw arguments and record the culling pass
+    pub fn cull(&self, encoder: &mut CommandEncoder, queue: &Queue, frustum_planes: &[Vec4; 6], camera_pos: Vec3) {
+        let mut lod_distances = [f32::MAX; 4];
+        lod_distances[..LOD_DISTANCES.len()].copy_from_slice(&LOD_DISTANCES);
+        let params = CullParams {
+            planes: frustum_planes.map(|p| p.to_array()),
+            camera_pos: camera_pos.extend(0.0).to_array(),
+            lod_distances,
+            skirt_depth: self.skirt_depth,
+            slot_count: self.slot_count,
+            lod_count: self.lod_count,
+            visible_stride: self.visible_stride,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        queue.write_buffer(&self.draw_buffer, 0, &self.empty_draws);
+
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Chunk Cull Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.slot_count.div_ceil(CULL_WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Record the terrain draws; `bind_groups` holds one chunk bind group for
+    /// multi-draw, or one per level otherwise (see `visible_binding`)
+    pub fn draw(&self, render_pass: &mut RenderPass, group: u32, bind_groups: &[BindGroup]) {
+        if self.multi_draw {
+            render_pass.set_bind_group(group, &bind_groups[0], &[]);
+            render_pass.multi_draw_indexed_indirect(&self.draw_buffer, 0, self.lod_count);
+        } else {
+            for lod in 0..self.lod_count {
+                render_pass.set_bind_group(group, &bind_groups[lod as usize], &[]);
+                render_pass.draw_indexed_indirect(&self.draw_buffer, lod as u64 * DRAW_ARGS_BYTES);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::camera::{CameraController, FlyCamera};
+    use crate::terrain::{ChunkCoord, HeightBounds};
+    use crate::webgpu::{test_gpu, GpuState};
+
+    fn read_buffer(gpu: &GpuState, buffer: &Buffer) -> Vec<u32> {
+        let readback = gpu.device.create_buffer(&BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: buffer.size(),
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = gpu.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(buffer, 0, &readback, 0, buffer.size());
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback.slice(..);
+        slice.map_async(MapMode::Read, |_| {});
+        gpu.device.poll(Maintain::Wait);
+        let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        data
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn gpu_culling_matches_cpu_reference() {
+        let gpu = test_gpu();
+        let quality = TerrainQuality {
+            view_radius: 12,
+            ..Default::default()
+        };
+        let lods: Vec<LodRange> = (0..4)
+            .map(|lod| LodRange {
+                first_index: lod * 100,
+                index_count: 60,
+            })
+            .collect();
+        let culler = ChunkCuller::new(&gpu.device, &quality, &lods);
+
+        // Fill every slot but the last few, which stay empty
+        let radius = quality.view_radius;
+        let mut coords = Vec::new();
+        for dz in -radius..=radius {
+            for dx in -radius..=radius {
+                coords.push(ChunkCoord::new(dx + 3, dz - 2));
+            }
+        }
+        coords.truncate(coords.len() - 5);
+        // Chunks at varied heights, so some are only visible thanks to their skirts or peaks
+        let bounds = |slot: usize| {
+            let min = (slot % 7) as f32 * 60.0 - 200.0;
+            HeightBounds { min, max: min + (slot % 3) as f32 * 80.0 }
+        };
+        for (slot, coord) in coords.iter().enumerate() {
+            let info = ChunkInfo {
+                world_offset: coord.world_offset(&quality),
+                height_offset: 0,
+                ready: 1,
+                min_height: bounds(slot).min,
+                max_height: bounds(slot).max,
+                ..Default::default()
+            };
+            culler.write_chunk(&gpu.queue, slot, info);
+        }
+
+        let mut camera = FlyCamera::new(16.0 / 9.0);
+        camera.position = Vec3::new(700.0, 150.0, -420.0);
+        camera.yaw = 2.2;
+        camera.pitch = -0.25;
+        let planes = camera.extract_frustum_planes();
+
+        let mut encoder = gpu.device.create_command_encoder(&Default::default());
+        culler.cull(&mut encoder, &gpu.queue, &planes, camera.position);
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let draws = read_buffer(&gpu, culler.draw_buffer());
+        let visible = read_buffer(&gpu, &culler.visible_buffer);
+        let mut expected = vec![Vec::new(); lods.len()];
+        for (slot, coord) in coords.iter().enumerate() {
+            let HeightBounds { min, max } = bounds(slot);
+            let with_skirt = HeightBounds { min: min - culler.skirt_depth, max };
+            if coord.is_visible_in_frustum(&planes, with_skirt, &quality) {
+                expected[coord.lod_for(camera.position, &quality).min(lods.len() - 1)].push(slot as u32);
+            }
+        }
+
+        assert!(expected.iter().all(|slots| !slots.is_empty()));
+        for (lod, expected) in expected.iter().enumerate() {
+            let args = &draws[lod * 5..lod * 5 + 5];
+            assert_eq!(args[0], lods[lod].index_count);
+            assert_eq!(args[1], expected.len() as u32);
+            assert_eq!(args[2], lods[lod].first_index);
+
+            let start = lod * culler.visible_stride as usize;
+            let mut actual = visible[start..start + expected.len()].to_vec();
+            actual.sort_unstable();
+            assert_eq!(&actual, expected);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::f32::consts::{PI, TAU};
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::sky::{SkyCycle, SunLight};
+
+pub const HOURS_PER_DAY: f32 = 24.0;
+pub const DEFAULT_TIME_OF_DAY: f32 = 12.0;
+pub const DEFAULT_TIME_SCALE: f32 = 0.1; // Game hours per real second (a day takes 4 minutes)
+
+const MOONLIGHT_INTENSITY: f32 = 0.25; // Moonlight relative to the moon's display color
+const MOONLIGHT_COLOR: [f32; 3] = [0.6, 0.7, 0.9]; // Used when there are no moons in the sky
+const DAYTIME_MOON_VISIBILITY: f32 = 0.25;
+const LIGHT_FADE_ELEVATION: f32 = 0.15; // Sine of the elevation where a light reaches full strength
+
+/// Sky colors, ambient light and fog at one point of the day
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Atmosphere {
+    pub sky_top: [f32; 3],
+    pub sky_horizon: [f32; 3],
+    pub ambient: f32,
+    pub fog_start: f32,
+    pub fog_distance: f32,
+}
+
+impl Atmosphere {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix3 = |a: [f32; 3], b: [f32; 3]| Vec3::from(a).lerp(Vec3::from(b), t).to_array();
+        Self {
+            sky_top: mix3(self.sky_top, other.sky_top),
+            sky_horizon: mix3(self.sky_horizon, other.sky_horizon),
+            ambient: self.ambient + (other.ambient - self.ambient) * t,
+            fog_start: self.fog_start + (other.fog_start - self.fog_start) * t,
+            fog_distance: self.fog_distance + (other.fog_distance - self.fog_distance) * t,
+        }
+    }
+}
+
+/// Atmosphere keyframes by hour, interpolated linearly and wrapping at midnight
+const KEYFRAMES: [(f32, Atmosphere); 5] = [
+    // Midnight
+    (0.0, Atmosphere {
+        sky_top: [0.01, 0.015, 0.04],
+        sky_horizon: [0.05, 0.07, 0.12],
+        ambient: 0.12,
+        fog_start: 500.0,
+        fog_distance: 2000.0,
+    }),
+    // Dawn
+    (6.0, Atmosphere {
+        sky_top: [0.2, 0.25, 0.45],
+        sky_horizon: [0.9, 0.55, 0.35],
+        ambient: 0.25,
+        fog_start: 400.0,
+        fog_distance: 2000.0,
+    }),
+    // Noon, matching the "Day" preset
+    (12.0, Atmosphere {
+        sky_top: [0.25, 0.5, 0.8],
+        sky_horizon: [0.75, 0.85, 0.95],
+        ambient: 0.35,
+        fog_start: 800.0,
+        fog_distance: 3000.0,
+    }),
+    // Dusk
+    (18.0, Atmosphere {
+        sky_top: [0.18, 0.2, 0.4],
+        sky_horizon: [0.95, 0.45, 0.25],
+        ambient: 0.25,
+        fog_start: 600.0,
+        fog_distance: 2500.0,
+    }),
+    // Evening
+    (20.5, Atmosphere {
+        sky_top: [0.03, 0.04, 0.1],
+        sky_horizon: [0.12, 0.12, 0.22],
+        ambient: 0.15,
+        fog_start: 500.0,
+        fog_distance: 2000.0,
+    }),
+];
+
+/// Time-of-day settings that can be modified at runtime
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayCycleSettings {
+    pub enabled: bool,      // When off, the sky and terrain settings are used as they are
+    pub time_of_day: f32,   // Hours, 0-24 (12 is noon)
+    pub time_scale: f32,    // Game hours per real second
+    pub paused: bool,
+}
+
+impl Default for DayCycleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time_of_day: DEFAULT_TIME_OF_DAY,
+            time_scale: DEFAULT_TIME_SCALE,
+            paused: false,
+        }
+    }
+}
+
+/// Moves the suns, moons and stars along an arc over the day and derives the
+/// atmosphere and terrain light from the time
+#[derive(Default)]
+pub struct DayCycle {
+    pub settings: DayCycleSettings,
+}
+
+impl DayCycle {
+    /// Advance the time of day by `dt` real seconds, unless disabled or paused
+    pub fn advance(&mut self, dt: f32) {
+        if self.settings.enabled && !self.settings.paused {
+            self.set_time_of_day(self.settings.time_of_day + dt * self.settings.time_scale);
+        }
+    }
+
+    /// Jump to a time of day in hours, wrapped into 0-24
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        self.settings.time_of_day = hours.rem_euclid(HOURS_PER_DAY);
+    }
+
+    /// Rotation of the suns about the world Z axis; zero at noon, where the
+    /// sky objects sit at their generated positions
+    pub fn sun_angle(&self) -> f32 {
+        (self.settings.time_of_day - DEFAULT_TIME_OF_DAY) / HOURS_PER_DAY * TAU
+    }
+
+    /// Moons and stars run half a day behind the suns, so they are up at midnight
+    pub fn night_angle(&self) -> f32 {
+        self.sun_angle() + PI
+    }
+
+    /// How dark it is, from 0 (sun overhead) to 1 (sun below the horizon)
+    pub fn night_factor(&self) -> f32 {
+        1.0 - smoothstep(-0.1, LIGHT_FADE_ELEVATION, self.sun_angle().cos())
+    }
+
+    /// Rotation and visibility of the sky objects
+    pub fn sky_cycle(&self) -> SkyCycle {
+        let night = self.night_factor();
+        SkyCycle {
+            sun_angle: self.sun_angle(),
+            night_angle: self.night_angle(),
+            star_visibility: night,
+            moon_visibility: night.max(DAYTIME_MOON_VISIBILITY),
+        }
+    }
+
+    /// Sky colors, ambient light and fog interpolated from the keyframes
+    pub fn atmosphere(&self) -> Atmosphere {
+        let hour = self.settings.time_of_day;
+        let next = KEYFRAMES
+            .iter()
+            .position(|(time, _)| *time > hour)
+            .unwrap_or(0);
+        let prev = (next + KEYFRAMES.len() - 1) % KEYFRAMES.len();
+
+        let (start, from) = KEYFRAMES[prev];
+        let (end, to) = KEYFRAMES[next];
+        let span = (end - start).rem_euclid(HOURS_PER_DAY);
+        let t = (hour - start).rem_euclid(HOURS_PER_DAY) / span;
+        from.lerp(&to, t)
+    }
+
+    /// Terrain light: the sun while it is up, otherwise the moon. `sun` and
+    /// `moon` are given at their noon positions and rotated to the current time;
+    /// the light fades out as its source nears the horizon.
+    pub fn light(&self, sun: SunLight, moon: Option<SunLight>) -> SunLight {
+        let sun_direction = rotate_z(sun.direction, self.sun_angle());
+        if sun_direction.y > 0.0 {
+            let fade = smoothstep(0.0, LIGHT_FADE_ELEVATION, sun_direction.y);
+            return SunLight {
+                direction: sun_direction,
+                color: (Vec3::from(sun.color) * fade).to_array(),
+            };
+        }
+
+        // Without moons in the sky, moonlight comes from opposite the sun
+        let moon = moon.unwrap_or(SunLight {
+            direction: sun.direction,
+            color: MOONLIGHT_COLOR,
+        });
+        let moon_direction = rotate_z(moon.direction, self.night_angle());
+        let fade = smoothstep(0.0, LIGHT_FADE_ELEVATION, moon_direction.y);
+        SunLight {
+            direction: moon_direction,
+            color: (Vec3::from(moon.color) * MOONLIGHT_INTENSITY * fade).to_array(),
+        }
+    }
+}
+
+/// Rotate about the world Z axis; must match `rotate_z` in sky.wgsl
+pub fn rotate_z(v: Vec3, angle: f32) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos, v.z)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hours: f32) -> DayCycle {
+        let mut cycle = DayCycle::default();
+        cycle.set_time_of_day(hours);
+        cycle
+    }
+
+    #[test]
+    fn atmosphere_interpolates_and_wraps() {
+        assert_eq!(at(12.0).atmosphere(), KEYFRAMES[2].1);
+        let morning = at(9.0).atmosphere();
+        assert!((morning.ambient - 0.3).abs() < 1e-5);
+
+        // Between the last keyframe and midnight, wrapping around the day
+        let late = at(22.25).atmosphere();
+        assert!((late.ambient - 0.135).abs() < 1e-5);
+        assert_eq!(at(24.0).atmosphere(), KEYFRAMES[0].1);
+    }
+
+    #[test]
+    fn sun_sets_and_moon_takes_over() {
+        let sun = SunLight {
+            direction: Vec3::Y,
+            color: [1.0, 1.0, 1.0],
+        };
+        let noon = at(12.0).light(sun, None);
+        assert!(noon.direction.abs_diff_eq(Vec3::Y, 1e-5));
+        assert_eq!(noon.color, [1.0, 1.0, 1.0]);
+
+        // Setting in the west by evening
+        let evening = at(17.0).light(sun, None);
+        assert!(evening.direction.x < 0.0 && evening.direction.y > 0.0);
+
+        // Moonlight at midnight, dim and from overhead
+        let midnight = at(0.0);
+        let light = midnight.light(sun, None);
+        assert!(light.direction.abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(light.color[2] < 0.5);
+        assert_eq!(midnight.sky_cycle().star_visibility, 1.0);
+        assert_eq!(at(12.0).sky_cycle().star_visibility, 0.0);
+
+        let mut cycle = at(23.5);
+        cycle.settings.enabled = true;
+        cycle.settings.time_scale = 1.0;
+        cycle.advance(1.0);
+        assert!((cycle.settings.time_of_day - 0.5).abs() < 1e-5);
+    }
+}
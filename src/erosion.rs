@@ -0,0 +1,463 @@
+use wgpu::*;
+
+use crate::terrain::{ao_border, TerrainQuality, TerrainSettings, HEIGHT_APRON};
+
+/// Upper bound on hydraulic iterations, which keeps the padded grid (and its cost) bounded
+pub const MAX_HYDRAULIC_ITERATIONS: u32 = 32;
+
+/// Upper bound on thermal iterations
+pub const MAX_THERMAL_ITERATIONS: u32 = 32;
+
+/// Each hydraulic iteration runs 4 passes that read one neighbour each
+const HYDRAULIC_BORDER_PER_ITERATION: u32 = 4;
+
+/// Each thermal iteration runs 2 passes that read one neighbour each
+const THERMAL_BORDER_PER_ITERATION: u32 = 2;
+
+/// Size of `Cell` in erosion.wgsl (vec4 + vec2 + 4 f32, padded to 16 bytes)
+const CELL_BYTES: u64 = 48;
+
+const EROSION_WORKGROUP_SIZE: u32 = 8; // Must match @workgroup_size in shader
+
+/// Extra samples needed on each side of a chunk so erosion near its edges
+/// sees the same terrain as its neighbours. Zero when erosion is disabled.
+pub(crate) fn erosion_border(settings: &TerrainSettings) -> u32 {
+    hydraulic_iterations(settings) * HYDRAULIC_BORDER_PER_ITERATION
+        + thermal_iterations(settings) * THERMAL_BORDER_PER_ITERATION
+}
+
+fn hydraulic_iterations(settings: &TerrainSettings) -> u32 {
+    settings.erosion_iterations.min(MAX_HYDRAULIC_ITERATIONS)
+}
+
+fn thermal_iterations(settings: &TerrainSettings) -> u32 {
+    settings.thermal_iterations.min(MAX_THERMAL_ITERATIONS)
+}
+
+/// Warn when the settings ask for more iterations than erosion will run
+pub(crate) fn warn_if_iterations_clamped(settings: &TerrainSettings) {
+    if settings.erosion_iterations > MAX_HYDRAULIC_ITERATIONS {
+        log::warn!(
+            "erosion_iterations {} exceeds the maximum of {}, clamping",
+            settings.erosion_iterations,
+            MAX_HYDRAULIC_ITERATIONS
+        );
+    }
+    if settings.thermal_iterations > MAX_THERMAL_ITERATIONS {
+        log::warn!(
+            "thermal_iterations {} exceeds the maximum of {}, clamping",
+            settings.thermal_iterations,
+            MAX_THERMAL_ITERATIONS
+        );
+    }
+}
+
+/// Samples generated on each side of a chunk: its height buffer apron plus the
+/// AO and erosion borders. Erosion runs on the whole grid, so the AO border
+/// sits inside the erosion border and only sees correctly eroded heights.
+pub(crate) fn generation_border(settings: &TerrainSettings, quality: &TerrainQuality) -> u32 {
+    HEIGHT_APRON + ao_border(settings, quality) + erosion_border(settings)
+}
+
+/// Padded scratch grid shared by all chunks, sized for `border` samples around a chunk
+struct ScratchBuffers {
+    heights: Buffer,
+    cells: Buffer,
+    border: u32,
+}
+
+/// Compute pipelines and shared scratch buffers for chunk erosion (hydraulic, then thermal).
+/// Chunks are eroded one at a time in a padded scratch grid and the
+/// interior is then copied into the chunk's height buffer.
+pub struct ErosionPipeline {
+    bind_group_layout: BindGroupLayout,
+    init_pipeline: ComputePipeline,
+    flux_pipeline: ComputePipeline,
+    water_pipeline: ComputePipeline,
+    erode_pipeline: ComputePipeline,
+    transport_pipeline: ComputePipeline,
+    settle_pipeline: ComputePipeline,
+    thermal_flux_pipeline: ComputePipeline,
+    thermal_apply_pipeline: ComputePipeline,
+    output_pipeline: ComputePipeline,
+
+    // Allocated on first use and grown with the generation border
+    scratch: Option<ScratchBuffers>,
+
+    quality: TerrainQuality,
+}
+
+impl ErosionPipeline {
+    pub fn new(device: &Device, quality: &TerrainQuality) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Erosion Shader"),
+            source: ShaderSource::Wgsl(include_str!("../shaders/erosion.wgsl").into()),
+        });
+
+        let storage_entry = |binding: u32| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Erosion Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1),
+                storage_entry(2),
+                storage_entry(3),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Erosion Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader_constants = quality.shader_constants();
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(&format!("Erosion Pipeline ({})", entry_point)),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: PipelineCompilationOptions {
+                    constants: &shader_constants,
+                    ..Default::default()
+                },
+                cache: None,
+            })
+        };
+
+        Self {
+            init_pipeline: create_pipeline("erosion_init"),
+            flux_pipeline: create_pipeline("erosion_flux"),
+            water_pipeline: create_pipeline("erosion_water"),
+            erode_pipeline: create_pipeline("erosion_erode"),
+            transport_pipeline: create_pipeline("erosion_transport"),
+            settle_pipeline: create_pipeline("erosion_settle"),
+            thermal_flux_pipeline: create_pipeline("thermal_flux"),
+            thermal_apply_pipeline: create_pipeline("thermal_apply"),
+            output_pipeline: create_pipeline("erosion_output"),
+            bind_group_layout,
+            scratch: None,
+            quality: *quality,
+        }
+    }
+
+    /// Samples per side of the padded generation grid
+    pub(crate) fn padded_size(&self, border: u32) -> u32 {
+        self.quality.chunk_size + 2 * border
+    }
+
+    /// Make room in the scratch grid for `border` samples around a chunk, allocating it on
+    /// first use and reallocating only when the border grows. Returns true when the
+    /// buffers were replaced, so bind groups made from them must be recreated.
+    pub fn reserve_scratch(&mut self, device: &Device, border: u32) -> bool {
+        if self.scratch.as_ref().is_some_and(|scratch| scratch.border >= border) {
+            return false;
+        }
+
+        let size = self.padded_size(border) as u64;
+        let cells = size * size;
+        let heights = device.create_buffer(&BufferDescriptor {
+            label: Some("Erosion Scratch Heights"),
+            size: cells * 4,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let cells = device.create_buffer(&BufferDescriptor {
+            label: Some("Erosion Cells"),
+            size: cells * CELL_BYTES,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        self.scratch = Some(ScratchBuffers { heights, cells, border });
+        true
+    }
+
+    /// Padded height grid that `compute_height` writes into when erosion or AO is enabled.
+    /// None until `reserve_scratch` is called.
+    pub fn scratch_heights(&self) -> Option<&Buffer> {
+        self.scratch.as_ref().map(|scratch| &scratch.heights)
+    }
+
+    /// Bind a chunk's params and height buffer range to the shared scratch buffers.
+    /// None until `reserve_scratch` is called.
+    pub fn create_bind_group(
+        &self,
+        device: &Device,
+        params_buffer: &Buffer,
+        height_binding: BufferBinding,
+        index: usize,
+    ) -> Option<BindGroup> {
+        let scratch = self.scratch.as_ref()?;
+        Some(device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("Chunk {} Erosion Bind Group", index)),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: scratch.heights.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: scratch.cells.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Buffer(height_binding),
+                },
+            ],
+        }))
+    }
+
+    /// Erode the padded grid in scratch and write the chunk and its apron to its height buffer.
+    /// Expects `compute_height` to have filled the scratch heights in the same pass.
+    pub fn dispatch(&self, compute_pass: &mut ComputePass, bind_group: &BindGroup, settings: &TerrainSettings) {
+        let workgroups = self
+            .padded_size(generation_border(settings, &self.quality))
+            .div_ceil(EROSION_WORKGROUP_SIZE);
+
+        compute_pass.set_bind_group(0, bind_group, &[]);
+
+        compute_pass.set_pipeline(&self.init_pipeline);
+        compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+
+        for _ in 0..hydraulic_iterations(settings) {
+            for pipeline in [
+                &self.flux_pipeline,
+                &self.water_pipeline,
+                &self.erode_pipeline,
+                &self.transport_pipeline,
+            ] {
+                compute_pass.set_pipeline(pipeline);
+                compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+            }
+        }
+
+        compute_pass.set_pipeline(&self.settle_pipeline);
+        compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+
+        for _ in 0..thermal_iterations(settings) {
+            for pipeline in [&self.thermal_flux_pipeline, &self.thermal_apply_pipeline] {
+                compute_pass.set_pipeline(pipeline);
+                compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+            }
+        }
+
+        let output_workgroups = self.padded_size(HEIGHT_APRON).div_ceil(EROSION_WORKGROUP_SIZE);
+        compute_pass.set_pipeline(&self.output_pipeline);
+        compute_pass.dispatch_workgroups(output_workgroups, output_workgroups, 1);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::sampler::TerrainSampler;
+    use crate::terrain::{ChunkCoord, ComputeParams};
+    use crate::webgpu::test_gpu;
+    use wgpu::util::DeviceExt;
+
+    /// Generate and erode chunks the same way `TerrainRenderer::generate_chunk` does,
+    /// reading back each chunk's heights (with apron).
+    fn eroded_chunks(settings: &TerrainSettings, coords: &[ChunkCoord]) -> Vec<Vec<f32>> {
+        let gpu = test_gpu();
+        let device = &gpu.device;
+        let quality = TerrainQuality::default();
+        let mut erosion = ErosionPipeline::new(device, &quality);
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Terrain Shader"),
+            source: ShaderSource::Wgsl(include_str!("../shaders/terrain.wgsl").into()),
+        });
+        let height_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Height Compute Pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: Some("compute_height"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let border = generation_border(settings, &quality);
+        erosion.reserve_scratch(device, border);
+        let grid_size = quality.height_grid_size();
+        let size = (grid_size * grid_size * 4) as u64;
+        let mut results = Vec::new();
+        for (i, coord) in coords.iter().enumerate() {
+            let params_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+                label: Some("Params Buffer"),
+                contents: bytemuck::cast_slice(&[ComputeParams::new(settings, &quality, *coord, border)]),
+                usage: BufferUsages::UNIFORM,
+            });
+            let height_buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("Height Buffer"),
+                size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+            let readback_buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("Readback Buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let height_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Scratch Compute Bind Group"),
+                layout: &height_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: erosion.scratch_heights().unwrap().as_entire_binding(),
+                    },
+                ],
+            });
+            let erosion_bind_group = erosion
+                .create_bind_group(device, &params_buffer, height_buffer.as_entire_buffer_binding(), i)
+                .unwrap();
+
+            let mut encoder = device.create_command_encoder(&Default::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_pipeline(&height_pipeline);
+                pass.set_bind_group(0, &height_bind_group, &[]);
+                let workgroups = erosion.padded_size(border).div_ceil(8);
+                pass.dispatch_workgroups(workgroups, workgroups, 1);
+                erosion.dispatch(&mut pass, &erosion_bind_group, settings);
+            }
+            encoder.copy_buffer_to_buffer(&height_buffer, 0, &readback_buffer, 0, size);
+            gpu.queue.submit(std::iter::once(encoder.finish()));
+
+            let slice = readback_buffer.slice(..);
+            slice.map_async(MapMode::Read, |_| {});
+            device.poll(Maintain::Wait);
+            results.push(bytemuck::cast_slice::<u8, f32>(&slice.get_mapped_range()).to_vec());
+        }
+        results
+    }
+
+    const EDGE_COORDS: [ChunkCoord; 3] = [
+        ChunkCoord { x: 2, z: -1 },
+        ChunkCoord { x: 3, z: -1 },
+        ChunkCoord { x: 2, z: 0 },
+    ];
+
+    /// Chunks are EDGE_COORDS: a chunk, its +X neighbour and its +Z neighbour
+    /// Every sample the chunks share, aprons included, must be bit-identical
+    fn assert_shared_edges(chunks: &[Vec<f32>]) {
+        let quality = TerrainQuality::default();
+        let n = quality.height_grid_size() as usize;
+        let shift = (quality.chunk_size - 1) as usize;
+        for chunk in chunks {
+            assert!(chunk.iter().all(|h| h.is_finite()));
+        }
+        for i in 0..n {
+            for j in 0..=2 * HEIGHT_APRON as usize {
+                assert_eq!(chunks[0][i * n + shift + j], chunks[1][i * n + j]);
+                assert_eq!(chunks[0][(shift + j) * n + i], chunks[2][j * n + i]);
+            }
+        }
+    }
+
+    /// Largest height difference between horizontally adjacent samples
+    fn max_step(heights: &[f32]) -> f32 {
+        let n = TerrainQuality::default().height_grid_size() as usize;
+        let mut step: f32 = 0.0;
+        for y in 0..n {
+            for x in 0..n - 1 {
+                step = step.max((heights[y * n + x] - heights[y * n + x + 1]).abs());
+            }
+        }
+        step
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn hydraulic_erosion_is_seamless() {
+        let settings = TerrainSettings {
+            pattern_type: 1,
+            octaves: 5,
+            erosion_iterations: 8,
+            ..Default::default()
+        };
+        let chunks = eroded_chunks(&settings, &EDGE_COORDS);
+        assert_shared_edges(&chunks);
+
+        // Erosion should actually move material
+        let uneroded = TerrainSampler::new(&settings).sample_grid(EDGE_COORDS[0], HEIGHT_APRON);
+        let max_change = chunks[0]
+            .iter()
+            .zip(&uneroded)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(max_change > 0.01, "erosion had no effect (max change {})", max_change);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn thermal_erosion_relaxes_steep_slopes() {
+        let settings = TerrainSettings {
+            pattern_type: 1,
+            octaves: 5,
+            height_scale: 400.0,
+            thermal_iterations: 16,
+            thermal_talus_angle: 30.0,
+            thermal_strength: 1.0,
+            ..Default::default()
+        };
+        let chunks = eroded_chunks(&settings, &EDGE_COORDS);
+        assert_shared_edges(&chunks);
+
+        let uneroded = TerrainSampler::new(&settings).sample_grid(EDGE_COORDS[0], HEIGHT_APRON);
+        let (before, after) = (max_step(&uneroded), max_step(&chunks[0]));
+        assert!(after < before, "steepest step {} -> {}", before, after);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn scratch_grows_only_with_the_border() {
+        let gpu = test_gpu();
+        let mut erosion = ErosionPipeline::new(&gpu.device, &TerrainQuality::default());
+        assert!(erosion.scratch_heights().is_none(), "nothing is allocated until needed");
+
+        let small = generation_border(&TerrainSettings::default(), &erosion.quality);
+        assert!(erosion.reserve_scratch(&gpu.device, small));
+        let padded = erosion.padded_size(small) as u64;
+        assert_eq!(erosion.scratch_heights().unwrap().size(), padded * padded * 4);
+
+        let eroded = TerrainSettings {
+            erosion_iterations: 8,
+            ..Default::default()
+        };
+        let large = generation_border(&eroded, &erosion.quality);
+        assert!(erosion.reserve_scratch(&gpu.device, large));
+        assert!(!erosion.reserve_scratch(&gpu.device, large));
+        assert!(!erosion.reserve_scratch(&gpu.device, small), "shrinking keeps the larger grid");
+    }
+}
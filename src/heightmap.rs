@@ -0,0 +1,408 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use exr::prelude::{Image, SpecificChannels, WritableImage};
+use glam::Vec2;
+
+use crate::erosion::erosion_border;
+use crate::sampler::TerrainSampler;
+use crate::terrain::{ChunkCoord, TerrainQuality, TerrainRenderer, TerrainSettings, HEIGHT_APRON};
+
+/// Rectangular area of the world to export
+#[derive(Clone, Copy, Debug)]
+pub enum ExportRegion {
+    /// Inclusive range of chunks, stitched along their shared edges
+    Chunks { min: ChunkCoord, max: ChunkCoord },
+    /// World-space XZ bounds, sampled on the chunk vertex spacing
+    World { min: Vec2, max: Vec2 },
+}
+
+/// Output file formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeightmapFormat {
+    /// 16-bit grayscale PNG, normalized from [-height_scale, height_scale]. The scale
+    /// and the measured min/max are stored in `height_scale`, `min_height` and
+    /// `max_height` text chunks.
+    Png16,
+    /// Headerless little-endian u16, normalized like `Png16`. The range isn't stored.
+    RawR16,
+    /// Headerless little-endian f32 in world units
+    RawR32,
+    /// Single-channel ("Y") 32-bit float OpenEXR in world units
+    Exr,
+}
+
+impl HeightmapFormat {
+    /// Pick a format from a file extension (png, r16, raw, r32, exr)
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(Self::Png16),
+            "r16" | "raw" => Some(Self::RawR16),
+            "r32" => Some(Self::RawR32),
+            "exr" => Some(Self::Exr),
+            _ => None,
+        }
+    }
+}
+
+/// A grid of terrain heights in world units, rows running along +Z
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    pub width: u32,
+    pub height: u32,
+    /// World-space XZ position of the first sample
+    pub origin: Vec2,
+    pub spacing: f32,
+    /// 16-bit formats map [-height_scale, height_scale] to their full range
+    pub height_scale: f32,
+    /// Lowest and highest sample, kept as metadata
+    pub min_height: f32,
+    pub max_height: f32,
+    pub heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Sample a region of the terrain on the CPU, on the chunk vertex spacing of `quality`.
+    /// Erosion only runs on the GPU, so this fails when it's enabled; use `from_terrain`.
+    pub fn sample(settings: &TerrainSettings, quality: &TerrainQuality, region: ExportRegion) -> Result<Self, String> {
+        if erosion_border(settings) > 0 {
+            return Err("Erosion only runs on the GPU; export eroded terrain with Heightmap::from_terrain".to_string());
+        }
+        let sampler = TerrainSampler::new(settings).with_quality(*quality);
+        let height_scale = settings.height_scale;
+        match region {
+            ExportRegion::Chunks { min, max } => Self::stitch_chunks(quality, height_scale, min, max, |coord| {
+                Ok(sampler.sample_grid(coord, HEIGHT_APRON))
+            }),
+            ExportRegion::World { min, max } => {
+                Self::sample_world(quality, height_scale, min, max, |x, z| Ok(sampler.height_at(x, z)))
+            }
+        }
+    }
+
+    /// Export a region of the loaded terrain from the heights generated on the GPU,
+    /// erosion included. Fails unless every chunk in the region has streamed in and
+    /// been read back (see `TerrainRenderer::finish_height_readback`).
+    pub fn from_terrain(terrain: &TerrainRenderer, region: ExportRegion) -> Result<Self, String> {
+        let quality = terrain.quality();
+        let height_scale = terrain.settings.height_scale;
+        match region {
+            ExportRegion::Chunks { min, max } => Self::stitch_chunks(quality, height_scale, min, max, |coord| {
+                terrain
+                    .chunk_heights(coord)
+                    .map(<[f32]>::to_vec)
+                    .ok_or_else(|| format!("Chunk {:?} heights haven't been read back yet", coord))
+            }),
+            ExportRegion::World { min, max } => Self::sample_world(quality, height_scale, min, max, |x, z| {
+                terrain
+                    .height_at(x, z)
+                    .ok_or_else(|| format!("Terrain heights at ({}, {}) haven't been read back yet", x, z))
+            }),
+        }
+    }
+
+    /// Stitch chunk grids, each laid out like `TerrainSampler::sample_grid` with `HEIGHT_APRON`
+    fn stitch_chunks(
+        quality: &TerrainQuality,
+        height_scale: f32,
+        min: ChunkCoord,
+        max: ChunkCoord,
+        chunk_grid: impl Fn(ChunkCoord) -> Result<Vec<f32>, String>,
+    ) -> Result<Self, String> {
+        if max.x < min.x || max.z < min.z {
+            return Err(format!("Empty chunk range: {:?}..={:?}", min, max));
+        }
+
+        // Neighbouring chunks share their edge row/column of samples
+        let chunk_size = quality.chunk_size as usize;
+        let grid_size = quality.height_grid_size() as usize;
+        let apron = HEIGHT_APRON as usize;
+        let edge = chunk_size - 1;
+        let chunks_x = (max.x - min.x + 1) as usize;
+        let chunks_z = (max.z - min.z + 1) as usize;
+        let width = chunks_x * edge + 1;
+        let height = chunks_z * edge + 1;
+
+        let mut heights = vec![0.0; width * height];
+        for cz in 0..chunks_z {
+            for cx in 0..chunks_x {
+                let coord = ChunkCoord::new(min.x + cx as i32, min.z + cz as i32);
+                let grid = chunk_grid(coord)?;
+                for y in 0..chunk_size {
+                    let row = (cz * edge + y) * width + cx * edge;
+                    let src = (y + apron) * grid_size + apron;
+                    heights[row..row + chunk_size].copy_from_slice(&grid[src..src + chunk_size]);
+                }
+            }
+        }
+
+        Ok(Self::new(
+            width as u32,
+            height as u32,
+            Vec2::from(min.world_offset(quality)),
+            quality.sample_spacing(),
+            height_scale,
+            heights,
+        ))
+    }
+
+    fn sample_world(
+        quality: &TerrainQuality,
+        height_scale: f32,
+        min: Vec2,
+        max: Vec2,
+        height_at: impl Fn(f32, f32) -> Result<f32, String>,
+    ) -> Result<Self, String> {
+        let size = max - min;
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return Err(format!("Empty world bounds: {} to {}", min, max));
+        }
+
+        let spacing = quality.sample_spacing();
+        let width = (size.x / spacing).ceil() as u32 + 1;
+        let height = (size.y / spacing).ceil() as u32 + 1;
+
+        let mut heights = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let pos = min + Vec2::new(x as f32, y as f32) * spacing;
+                heights.push(height_at(pos.x, pos.y)?);
+            }
+        }
+
+        Ok(Self::new(width, height, min, spacing, height_scale, heights))
+    }
+
+    fn new(width: u32, height: u32, origin: Vec2, spacing: f32, height_scale: f32, heights: Vec<f32>) -> Self {
+        let min_height = heights.iter().copied().fold(f32::MAX, f32::min);
+        let max_height = heights.iter().copied().fold(f32::MIN, f32::max);
+        Self {
+            width,
+            height,
+            origin,
+            spacing,
+            height_scale,
+            min_height,
+            max_height,
+            heights,
+        }
+    }
+
+    /// Heights mapped from [-height_scale, height_scale] to [0, 1], clamping the
+    /// samples outside that range
+    pub fn normalized(&self) -> impl Iterator<Item = f32> + '_ {
+        let scale = if self.height_scale > 0.0 { 0.5 / self.height_scale } else { 0.0 };
+        self.heights.iter().map(move |h| (h * scale + 0.5).clamp(0.0, 1.0))
+    }
+
+    fn to_u16(&self) -> Vec<u16> {
+        if self.min_height < -self.height_scale || self.max_height > self.height_scale {
+            log::warn!(
+                "Heights from {} to {} exceed the 16-bit range of +/-{}, clamping",
+                self.min_height,
+                self.max_height,
+                self.height_scale
+            );
+        }
+        self.normalized()
+            .map(|n| (n * u16::MAX as f32).round() as u16)
+            .collect()
+    }
+
+    /// Encode as headerless RAW bytes (`RawR16` or `RawR32`)
+    pub fn to_raw_bytes(&self, format: HeightmapFormat) -> Result<Vec<u8>, String> {
+        match format {
+            HeightmapFormat::RawR16 => Ok(self.to_u16().iter().flat_map(|v| v.to_le_bytes()).collect()),
+            HeightmapFormat::RawR32 => Ok(self.heights.iter().flat_map(|v| v.to_le_bytes()).collect()),
+            _ => Err(format!("{:?} is not a RAW format", format)),
+        }
+    }
+
+    /// Write the heightmap to a file in the given format
+    pub fn write(&self, path: &Path, format: HeightmapFormat) -> Result<(), String> {
+        match format {
+            HeightmapFormat::Png16 => self.write_png(path),
+            HeightmapFormat::RawR16 | HeightmapFormat::RawR32 => {
+                let bytes = self.to_raw_bytes(format)?;
+                let mut file = File::create(path)
+                    .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+                file.write_all(&bytes)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+            }
+            HeightmapFormat::Exr => self.write_exr(path),
+        }
+    }
+
+    fn write_png(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        for (keyword, value) in [
+            ("height_scale", self.height_scale),
+            ("min_height", self.min_height),
+            ("max_height", self.max_height),
+        ] {
+            encoder
+                .add_text_chunk(keyword.to_string(), value.to_string())
+                .map_err(|e| format!("Failed to add PNG text chunk: {}", e))?;
+        }
+
+        // PNG stores 16-bit samples big-endian
+        let data: Vec<u8> = self.to_u16().iter().flat_map(|v| v.to_be_bytes()).collect();
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+        writer
+            .write_image_data(&data)
+            .map_err(|e| format!("Failed to write PNG data: {}", e))
+    }
+
+    fn write_exr(&self, path: &Path) -> Result<(), String> {
+        let width = self.width as usize;
+        let channels = SpecificChannels::build()
+            .with_channel("Y")
+            .with_pixel_fn(|pos| (self.heights[pos.y() * width + pos.x()],));
+        Image::from_channels((width, self.height as usize), channels)
+            .write()
+            .to_file(path)
+            .map_err(|e| format!("Failed to write EXR {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stitched_chunks_match_point_samples() {
+        let settings = TerrainSettings::default();
+        let region = ExportRegion::Chunks {
+            min: ChunkCoord::new(-1, 2),
+            max: ChunkCoord::new(1, 3),
+        };
+        let map = Heightmap::sample(&settings, &TerrainQuality::default(), region).unwrap();
+        assert_eq!((map.width, map.height), (3 * 63 + 1, 2 * 63 + 1));
+
+        let sampler = TerrainSampler::new(&settings);
+        for (x, y) in [(0, 0), (63, 0), (100, 70), (189, 126)] {
+            let world = map.origin + Vec2::new(x as f32, y as f32) * map.spacing;
+            let expected = sampler.height_at(world.x, world.y);
+            let actual = map.heights[y * map.width as usize + x];
+            assert!((expected - actual).abs() <= 1e-3 * settings.height_scale);
+        }
+    }
+
+    #[test]
+    fn writes_all_formats() {
+        let settings = TerrainSettings::default();
+        let region = ExportRegion::World {
+            min: Vec2::new(-100.0, 50.0),
+            max: Vec2::new(100.0, 150.0),
+        };
+        let map = Heightmap::sample(&settings, &TerrainQuality::default(), region).unwrap();
+        let samples = (map.width * map.height) as usize;
+
+        let r16 = map.to_raw_bytes(HeightmapFormat::RawR16).unwrap();
+        assert_eq!(r16.len(), samples * 2);
+        // 16-bit samples map +/-height_scale to the full range, whatever the region's extent
+        let r16: Vec<u16> = r16.chunks(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+        for (&v, &h) in r16.iter().zip(&map.heights) {
+            let expected = (h / settings.height_scale * 0.5 + 0.5) * u16::MAX as f32;
+            assert!((v as f32 - expected).abs() <= 0.5, "{} vs {}", v, expected);
+        }
+        let r32 = map.to_raw_bytes(HeightmapFormat::RawR32).unwrap();
+        assert_eq!(f32::from_le_bytes(r32[..4].try_into().unwrap()), map.heights[0]);
+
+        let dir = std::env::temp_dir();
+        for (name, format) in [("heightmap_test.png", HeightmapFormat::Png16), ("heightmap_test.exr", HeightmapFormat::Exr)] {
+            let path = dir.join(name);
+            assert_eq!(HeightmapFormat::from_extension(&path), Some(format));
+            map.write(&path, format).unwrap();
+            if format == HeightmapFormat::Png16 {
+                let reader = png::Decoder::new(File::open(&path).unwrap()).read_info().unwrap();
+                assert_eq!((reader.info().width, reader.info().height), (map.width, map.height));
+                assert_eq!(reader.info().bit_depth, png::BitDepth::Sixteen);
+                let text = &reader.info().uncompressed_latin1_text;
+                assert!(text.iter().any(|t| t.keyword == "height_scale" && t.text == map.height_scale.to_string()));
+                assert!(text.iter().any(|t| t.keyword == "min_height" && t.text == map.min_height.to_string()));
+                assert!(text.iter().any(|t| t.keyword == "max_height" && t.text == map.max_height.to_string()));
+            } else {
+                assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            }
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn clamps_heights_outside_the_scale() {
+        let map = Heightmap::new(3, 1, Vec2::ZERO, 1.0, 10.0, vec![-25.0, 0.0, 12.0]);
+        assert_eq!((map.min_height, map.max_height), (-25.0, 12.0));
+        let r16 = map.to_raw_bytes(HeightmapFormat::RawR16).unwrap();
+        let r16: Vec<u16> = r16.chunks(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+        assert_eq!(r16, [0, 32768, u16::MAX]);
+    }
+
+    #[test]
+    fn sampling_refuses_eroded_terrain() {
+        let settings = TerrainSettings {
+            erosion_iterations: 4,
+            ..Default::default()
+        };
+        let region = ExportRegion::Chunks {
+            min: ChunkCoord::new(0, 0),
+            max: ChunkCoord::new(0, 0),
+        };
+        assert!(Heightmap::sample(&settings, &TerrainQuality::default(), region).is_err());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn exports_eroded_heights_read_back_from_the_gpu() {
+        use crate::camera::FlyCamera;
+        use crate::webgpu::test_gpu;
+
+        let gpu = test_gpu();
+        let settings = TerrainSettings {
+            erosion_iterations: 4,
+            ..Default::default()
+        };
+        let quality = TerrainQuality {
+            view_radius: 1,
+            ..Default::default()
+        };
+        let mut terrain = TerrainRenderer::new(&gpu.device, gpu.surface_format, settings.clone(), quality).unwrap();
+        let region = ExportRegion::Chunks {
+            min: ChunkCoord::new(-1, 0),
+            max: ChunkCoord::new(0, 1),
+        };
+        terrain.finish_streaming(&gpu.device, &gpu.queue, &FlyCamera::new(1.0));
+        assert!(Heightmap::from_terrain(&terrain, region).is_err(), "heights aren't read back yet");
+        terrain.finish_height_readback(&gpu.device, &gpu.queue);
+
+        let map = Heightmap::from_terrain(&terrain, region).unwrap();
+        assert_eq!((map.width, map.height), (2 * 63 + 1, 2 * 63 + 1));
+        for (x, y) in [(0, 0), (63, 10), (100, 125)] {
+            let world = map.origin + Vec2::new(x as f32, y as f32) * map.spacing;
+            let expected = terrain.height_at(world.x, world.y).unwrap();
+            assert!((map.heights[y * map.width as usize + x] - expected).abs() < 1e-3);
+        }
+
+        // Erosion moved the terrain away from the CPU heights
+        let uneroded = TerrainSettings {
+            erosion_iterations: 0,
+            ..settings
+        };
+        let cpu = Heightmap::sample(&uneroded, &quality, region).unwrap();
+        assert!(map.heights.iter().zip(&cpu.heights).any(|(g, c)| (g - c).abs() > 1e-2));
+
+        let outside = ExportRegion::World {
+            min: Vec2::new(0.0, 0.0),
+            max: Vec2::new(3.0 * quality.chunk_world_size, 10.0),
+        };
+        assert!(Heightmap::from_terrain(&terrain, outside).is_err());
+    }
+}
@@ -1,13 +1,16 @@
-use std::cell::RefCell;
 use std::collections::HashSet;
-use std::rc::Rc;
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+use std::{cell::RefCell, rc::Rc};
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+use wasm_bindgen::{prelude::*, JsCast};
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 use web_sys::{Document, HtmlCanvasElement, KeyboardEvent, MouseEvent, WheelEvent, Window};
 
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 use crate::AppState;
 
 /// Helper to get window and document, returning None if unavailable
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 fn get_window_document() -> Option<(Window, Document)> {
     let window = web_sys::window()?;
     let document = window.document()?;
@@ -20,6 +23,7 @@ pub struct InputState {
     pub mouse_delta_x: f32,
     pub mouse_delta_y: f32,
     pub mouse_locked: bool,
+    pub mouse_down: bool, // Primary button held, for dragging
     pub scroll_delta: f32,
 }
 
@@ -30,6 +34,7 @@ impl InputState {
             mouse_delta_x: 0.0,
             mouse_delta_y: 0.0,
             mouse_locked: false,
+            mouse_down: false,
             scroll_delta: 0.0,
         }
     }
@@ -52,6 +57,7 @@ impl Default for InputState {
 }
 
 /// Setup all input event handlers
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 pub fn setup_input_handlers(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppState>>) {
     let Some((_window, document)) = get_window_document() else {
         log::warn!("Could not access window/document for input handlers");
@@ -67,10 +73,19 @@ pub fn setup_input_handlers(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppSta
             if matches!(
                 key.as_str(),
                 "w" | "a" | "s" | "d" | " " | "shift" | "tab" | "r" | "q" | "e" | "u" | "i" | "o" | "j" | "k" | "l"
+                    | "g" | "c" | "f" | "x"
             ) {
                 event.prevent_default();
             }
             let mut state = state.borrow_mut();
+            // G toggles between flying and walking
+            if key == "g" && !event.repeat() {
+                state.camera_mut().toggle_mode();
+            }
+            // C toggles the orbit camera
+            if key == "c" && !event.repeat() {
+                state.toggle_orbit_camera();
+            }
             state.input_mut().keys.insert(key);
         }) as Box<dyn FnMut(_)>);
 
@@ -96,7 +111,7 @@ pub fn setup_input_handlers(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppSta
         let closure = Closure::wrap(Box::new(move |event: MouseEvent| {
             let mut state = state.borrow_mut();
             let input = state.input_mut();
-            if input.mouse_locked {
+            if input.mouse_locked || input.mouse_down {
                 input.mouse_delta_x += event.movement_x() as f32;
                 input.mouse_delta_y += event.movement_y() as f32;
             }
@@ -106,6 +121,30 @@ pub fn setup_input_handlers(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppSta
         closure.forget();
     }
 
+    // Mouse buttons (drag)
+    {
+        let state = Rc::clone(&state);
+        let closure = Closure::wrap(Box::new(move |event: MouseEvent| {
+            if event.button() == 0 {
+                state.borrow_mut().input_mut().mouse_down = true;
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        let _ = canvas.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+    {
+        let state = Rc::clone(&state);
+        let closure = Closure::wrap(Box::new(move |event: MouseEvent| {
+            if event.button() == 0 {
+                state.borrow_mut().input_mut().mouse_down = false;
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        let _ = document.add_event_listener_with_callback("mouseup", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+
     // Mouse wheel (zoom)
     {
         let state = Rc::clone(&state);
@@ -124,7 +163,7 @@ pub fn setup_input_handlers(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppSta
     {
         let canvas_clone = canvas.clone();
         let closure = Closure::wrap(Box::new(move |_: MouseEvent| {
-            let _ = canvas_clone.request_pointer_lock();
+            canvas_clone.request_pointer_lock();
         }) as Box<dyn FnMut(_)>);
 
         let _ = canvas.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
@@ -157,6 +196,7 @@ pub fn setup_input_handlers(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppSta
 }
 
 /// Normalize key names for consistent handling
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 fn normalize_key(key: &str) -> String {
     match key {
         "Shift" => "shift".to_string(),
@@ -1,87 +1,82 @@
-mod camera;
-mod input;
-mod particles;
-mod presets;
-mod sky;
-mod terrain;
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+mod bindings;
+pub mod camera;
+pub mod camera_path;
+pub mod culling;
+pub mod daycycle;
+pub mod erosion;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod heightmap;
+pub mod input;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mesh_export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod offline;
+pub mod particles;
+pub mod presets;
+pub mod sampler;
+pub mod shadows;
+pub mod sky;
+pub mod terrain;
 mod utils;
-mod webgpu;
+pub mod water;
+pub mod webgpu;
 
-use std::cell::RefCell;
-use std::rc::Rc;
-use js_sys::Math;
-use serde::Serialize;
-use wasm_bindgen::prelude::*;
-use web_sys::HtmlCanvasElement;
+use glam::Vec3;
 
-use camera::FlyCamera;
+use camera::{CameraRig, CameraSettings, ControllerKind, FlyCamera};
+use camera_path::CameraPath;
+use daycycle::{DayCycle, DayCycleSettings};
 use input::InputState;
 use particles::{ParticleSettings, ParticleSystem};
-use sky::{SkyRenderer, SkySettings};
-use terrain::{TerrainRenderer, TerrainSettings};
+use presets::FullPreset;
+use sampler::{raycast_heightfield, TerrainSampler};
+use shadows::ShadowSettings;
+use sky::{SkyCycle, SkyRenderer, SkySettings};
+use terrain::{HeightBounds, TerrainQuality, TerrainRenderer, TerrainSettings};
 use webgpu::GpuState;
 
-// Global state for JS access
-thread_local! {
-    static APP_STATE: RefCell<Option<Rc<RefCell<AppState>>>> = RefCell::new(None);
-}
-
-/// Helper to access APP_STATE with mutable access and automatic error handling
-fn with_app_state_mut<F, T>(f: F) -> Result<T, JsValue>
-where
-    F: FnOnce(&mut AppState) -> T,
-{
-    APP_STATE.with(|s| {
-        if let Some(state) = s.borrow().as_ref() {
-            Ok(f(&mut state.borrow_mut()))
-        } else {
-            Err(JsValue::from_str("App not initialized"))
-        }
-    })
-}
-
-/// Helper to access APP_STATE with read-only access and automatic error handling
-fn with_app_state<F, T>(f: F) -> Result<T, JsValue>
-where
-    F: FnOnce(&AppState) -> T,
-{
-    APP_STATE.with(|s| {
-        if let Some(state) = s.borrow().as_ref() {
-            Ok(f(&state.borrow()))
-        } else {
-            Err(JsValue::from_str("App not initialized"))
-        }
-    })
-}
-
 /// Main application state
 pub struct AppState {
     gpu: GpuState,
-    camera: FlyCamera,
+    camera: CameraRig,
     input: InputState,
     terrain: TerrainRenderer,
     sky: SkyRenderer,
     particles: ParticleSystem,
+    day_cycle: DayCycle,
     last_time: f64,
+
+    // Camera paths, with the seconds elapsed since recording/playback started
+    recording: Option<(CameraPath, f32)>,
+    playback: Option<(CameraPath, f32)>,
 }
 
 impl AppState {
-    pub async fn new(canvas: HtmlCanvasElement) -> Result<Self, String> {
-        let width = canvas.width();
-        let height = canvas.height();
+    /// Build the renderers on an initialized GPU, using the default preset and the given seed
+    pub fn new(gpu: GpuState, seed: u32) -> Result<Self, String> {
+        Self::with_preset(gpu, presets::get_default_preset(), seed)
+    }
 
-        let gpu = GpuState::new(&canvas).await?;
-        let camera = FlyCamera::new(width as f32 / height as f32);
-        let input = InputState::new();
+    /// Build the renderers on an initialized GPU with a specific preset
+    pub fn from_preset(gpu: GpuState, preset: FullPreset, seed: u32) -> Result<Self, String> {
+        Self::with_preset(gpu, Some(preset), seed)
+    }
 
-        // Get preset settings first so terrain is generated with correct settings
-        let preset = presets::get_default_preset();
+    fn with_preset(gpu: GpuState, preset: Option<FullPreset>, seed: u32) -> Result<Self, String> {
+        let camera = CameraRig::new(gpu.config.width as f32 / gpu.config.height as f32);
+        let input = InputState::new();
 
         // Create terrain renderer with correct initial settings
         let mut terrain_settings = preset.as_ref().map(|p| p.terrain.clone()).unwrap_or_default();
-        // Randomize seed like clicking a preset button
-        terrain_settings.seed = (Math::random() * 1000000.0) as u32;
-        let terrain = TerrainRenderer::new(&gpu.device, &gpu.queue, gpu.surface_format, terrain_settings)?;
+        terrain_settings.seed = seed;
+        let terrain = TerrainRenderer::new(
+            &gpu.device,
+            gpu.surface_format,
+            terrain_settings,
+            TerrainQuality::default(),
+        )?;
 
         let mut sky = SkyRenderer::new(&gpu.device, gpu.surface_format)?;
         let mut particles = ParticleSystem::new(&gpu.device, gpu.surface_format)?;
@@ -99,7 +94,10 @@ impl AppState {
             terrain,
             sky,
             particles,
+            day_cycle: DayCycle::default(),
             last_time: 0.0,
+            recording: None,
+            playback: None,
         })
     }
 
@@ -111,53 +109,70 @@ impl AppState {
         };
         self.last_time = current_time;
 
-        // Update camera based on input
-        self.camera.update(&self.input, dt);
+        // Update camera based on input, unless a recorded path is playing
+        if let Some((path, elapsed)) = &mut self.playback {
+            *elapsed += dt;
+            if let Some(pose) = path.sample(*elapsed) {
+                pose.apply(&mut self.camera.fly);
+            }
+            if *elapsed >= path.duration() {
+                self.playback = None;
+                log::info!("Camera path playback finished");
+            }
+        } else {
+            // Heights read back from the GPU, so walking follows the eroded terrain
+            let terrain = &self.terrain;
+            self.camera
+                .active_mut()
+                .update(&self.input, dt, &|x, z| terrain.height_at(x, z));
+        }
+        if let Some((path, elapsed)) = &mut self.recording {
+            *elapsed += dt;
+            path.record(*elapsed, &self.camera.fly);
+        }
 
         // Clear per-frame input state
         self.input.clear_frame_state();
 
-        // Check if terrain needs regeneration (settings changed or R key pressed)
-        self.terrain
-            .check_regeneration(&self.gpu.device, &self.gpu.queue, self.camera.position);
-
-        // Update terrain chunks based on camera position
-        self.terrain
-            .update(&self.gpu.device, &self.gpu.queue, self.camera.position);
-
         // Update sky (animations, regeneration check)
         self.sky.update(dt);
         self.sky.check_regeneration();
 
+        // Check if terrain needs regeneration (settings changed or R key pressed)
+        self.terrain.check_regeneration();
+
+        // Light the terrain from the sun in the sky, moved along its arc by the day/night cycle
+        if self.day_cycle.settings.enabled {
+            self.day_cycle.advance(dt);
+            // Without suns in the sky, the settings light is the noon sun
+            let sun = self.sky.primary_sun().unwrap_or(self.terrain.settings_sun());
+            let light = self.day_cycle.light(sun, self.sky.primary_moon());
+            self.sky.set_cycle(self.day_cycle.sky_cycle());
+            self.terrain.set_atmosphere(Some(self.day_cycle.atmosphere()));
+            self.terrain.set_sky_sun(Some(light));
+        } else {
+            self.sky.set_cycle(SkyCycle::default());
+            self.terrain.set_atmosphere(None);
+            self.terrain.set_sky_sun(self.sky.primary_sun());
+        }
+
+        // Stream terrain chunks around the camera, nearest and visible first
+        self.terrain
+            .update(&self.gpu.device, &self.gpu.queue, self.camera.active(), dt);
+
         // Return dt for use in render (particles need it)
         dt
     }
 
     pub fn render(&mut self, dt: f32) {
-        let output = match self.gpu.surface.get_current_texture() {
-            Ok(output) => output,
-            Err(e) => match e {
-                wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => {
-                    self.gpu.resize(self.gpu.config.width, self.gpu.config.height);
-                    return;
-                }
-                wgpu::SurfaceError::Timeout => {
-                    log::warn!("Surface timeout, skipping frame");
-                    return;
-                }
-                wgpu::SurfaceError::OutOfMemory => {
-                    log::error!("Surface out of memory, stopping render loop");
-                    return;
-                }
-            },
+        let Some(frame) = self.gpu.acquire_frame() else {
+            return;
         };
+        let view = &frame.view;
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        let view_proj = self.camera.view_projection_matrix().to_cols_array_2d();
-        let camera_pos = self.camera.position;
+        let camera = self.camera.active();
+        let view_proj = camera.view_projection_matrix().to_cols_array_2d();
+        let camera_pos = camera.position();
 
         // Create a SINGLE encoder for both compute and render passes
         // This ensures proper GPU command ordering - compute finishes before render reads
@@ -171,21 +186,22 @@ impl AppState {
         // FIRST: Run particle compute pass (updates particle positions)
         // This must happen before render passes that read particle data
         self.particles
-            .update(&mut encoder, &self.gpu.queue, self.camera.position, dt);
+            .update(&mut encoder, &self.gpu.queue, camera_pos, dt);
 
-        // Run terrain rendering (clears to sky horizon color)
+        // Run terrain rendering (clears to sky horizon color), then the water over it
         self.terrain.render(
+            &self.gpu.device,
             &mut encoder,
-            &view,
+            view,
             &self.gpu.depth_view,
-            &self.camera,
+            camera,
             &self.gpu.queue,
         );
 
         // Render sky objects (no depth test, blends on top of sky areas)
         self.sky.render(
             &mut encoder,
-            &view,
+            view,
             view_proj,
             camera_pos,
             &self.gpu.queue,
@@ -195,7 +211,7 @@ impl AppState {
         // Now reads from the buffer that compute just wrote to
         self.particles.render(
             &mut encoder,
-            &view,
+            view,
             &self.gpu.depth_view,
             view_proj,
             camera_pos,
@@ -204,269 +220,214 @@ impl AppState {
 
         // Submit all commands together - GPU executes them in order
         self.gpu.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        frame.present();
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
             self.gpu.resize(width, height);
-            self.camera.aspect = width as f32 / height as f32;
+            self.camera.set_aspect(width as f32 / height as f32);
         }
     }
 
-    pub fn input_mut(&mut self) -> &mut InputState {
-        &mut self.input
+    pub fn gpu(&self) -> &GpuState {
+        &self.gpu
     }
 
-    pub fn update_terrain_settings(&mut self, settings: TerrainSettings) {
-        self.terrain.update_settings(settings);
+    /// The terrain renderer, e.g. to export its read-back heights
+    pub fn terrain(&self) -> &TerrainRenderer {
+        &self.terrain
     }
 
-    pub fn get_terrain_settings(&self) -> &TerrainSettings {
-        &self.terrain.settings
+    /// The fly camera, whether or not it is the active controller
+    pub fn camera_mut(&mut self) -> &mut FlyCamera {
+        &mut self.camera.fly
     }
 
-    pub fn queue_terrain_regeneration(&mut self) {
-        self.terrain.queue_regeneration();
+    /// Switch between the fly camera and orbiting the terrain point it looks at
+    pub fn toggle_orbit_camera(&mut self) {
+        match self.camera.active_kind() {
+            ControllerKind::Fly => {
+                let fly = &self.camera.fly;
+                let hit = self.raycast_terrain(fly.position, fly.forward_direction());
+                let target = hit.unwrap_or_else(|| {
+                    // Looking at the sky: orbit the ground in front of the camera, or a
+                    // point at eye level until its heights have been read back
+                    let ahead = fly.position + Vec3::new(fly.yaw.sin(), 0.0, fly.yaw.cos()) * 300.0;
+                    let ground = self.terrain_height_at(ahead.x, ahead.z).unwrap_or(fly.position.y);
+                    Vec3::new(ahead.x, ground, ahead.z)
+                });
+                self.camera.orbit_around(target);
+            }
+            ControllerKind::Orbit => self.camera.fly_from_orbit(),
+        }
+        log::info!("Camera controller: {:?}", self.camera.active_kind());
     }
 
-    pub fn update_sky_settings(&mut self, settings: SkySettings) {
-        self.sky.update_settings(settings);
+    /// Start recording fly camera poses (switches to the fly camera)
+    pub fn start_camera_recording(&mut self) {
+        self.camera.fly_from_orbit();
+        let mut path = CameraPath::default();
+        path.record(0.0, &self.camera.fly);
+        self.recording = Some((path, 0.0));
+        log::info!("Camera path recording started");
     }
 
-    pub fn get_sky_settings(&self) -> &SkySettings {
-        &self.sky.settings
+    /// Stop recording and return the path, if recording
+    pub fn stop_camera_recording(&mut self) -> Option<CameraPath> {
+        let (path, _) = self.recording.take()?;
+        log::info!("Camera path recorded: {} keyframes over {:.1}s", path.keyframes.len(), path.duration());
+        Some(path)
     }
 
-    pub fn update_particle_settings(&mut self, settings: ParticleSettings) {
-        self.particles.update_settings(settings);
+    /// Fly the camera along a path from its start; input is ignored until it ends
+    pub fn play_camera_path(&mut self, path: CameraPath) -> Result<(), String> {
+        if path.keyframes.is_empty() {
+            return Err("Camera path has no keyframes".to_string());
+        }
+        self.camera.fly_from_orbit();
+        self.playback = Some((path, 0.0));
+        Ok(())
     }
 
-    pub fn get_particle_settings(&self) -> &ParticleSettings {
-        &self.particles.settings
+    pub fn stop_camera_playback(&mut self) {
+        self.playback = None;
     }
-}
 
-#[wasm_bindgen(start)]
-pub async fn run() {
-    utils::init();
-
-    log::info!("Starting Procedural Terrain Simulator...");
-
-    // Get canvas from DOM
-    let window = web_sys::window().expect("No window");
-    let document = window.document().expect("No document");
-    let canvas = document
-        .get_element_by_id("canvas")
-        .expect("No canvas element with id 'canvas'")
-        .dyn_into::<HtmlCanvasElement>()
-        .expect("Element is not a canvas");
-
-    // Initialize app state
-    let state = match AppState::new(canvas.clone()).await {
-        Ok(state) => Rc::new(RefCell::new(state)),
-        Err(e) => {
-            log::error!("Failed to initialize: {}", e);
-            show_error(&document, &format!("WebGPU initialization failed: {}", e));
-            return;
-        }
-    };
+    pub fn is_playing_camera_path(&self) -> bool {
+        self.playback.is_some()
+    }
 
-    // Store state globally for JS access
-    APP_STATE.with(|s| {
-        *s.borrow_mut() = Some(Rc::clone(&state));
-    });
+    /// Turntable rotation for the orbit camera
+    pub fn set_orbit_auto_rotate(&mut self, enabled: bool, speed: f32) {
+        self.camera.orbit.auto_rotate = enabled;
+        self.camera.orbit.auto_rotate_speed = speed;
+    }
 
-    // Setup input handlers
-    input::setup_input_handlers(&canvas, Rc::clone(&state));
+    pub fn get_camera_settings(&self) -> &CameraSettings {
+        &self.camera.fly.settings
+    }
 
-    // Setup resize handler
-    setup_resize_handler(&canvas, Rc::clone(&state));
+    pub fn update_camera_settings(&mut self, settings: CameraSettings) {
+        self.camera.fly.settings = settings;
+    }
 
-    // Start the frame loop
-    start_frame_loop(state);
+    /// Minimum fly camera height above the terrain; None lets it pass through
+    pub fn set_camera_ground_clearance(&mut self, clearance: Option<f32>) {
+        self.camera.fly.ground_clearance = clearance;
+    }
 
-    log::info!("Initialization complete. Click canvas to capture mouse.");
-}
+    pub fn input_mut(&mut self) -> &mut InputState {
+        &mut self.input
+    }
 
-fn show_error(document: &web_sys::Document, message: &str) {
-    if let Some(error_el) = document.get_element_by_id("error") {
-        error_el.set_text_content(Some(message));
-        let _ = error_el
-            .dyn_ref::<web_sys::HtmlElement>()
-            .map(|el| el.style().set_property("display", "block"));
+    pub fn update_terrain_settings(&mut self, settings: TerrainSettings) {
+        self.terrain.update_settings(settings);
     }
-}
 
-fn setup_resize_handler(canvas: &HtmlCanvasElement, state: Rc<RefCell<AppState>>) {
-    let canvas_clone = canvas.clone();
-    let closure = Closure::wrap(Box::new(move || {
-        let Some(window) = web_sys::window() else { return };
-        let logical_width = window
-            .inner_width()
-            .ok()
-            .and_then(|v| v.as_f64())
-            .unwrap_or(800.0);
-        let logical_height = window
-            .inner_height()
-            .ok()
-            .and_then(|v| v.as_f64())
-            .unwrap_or(600.0);
-        let dpr = window.device_pixel_ratio();
-        let physical_width = (logical_width * dpr).round() as u32;
-        let physical_height = (logical_height * dpr).round() as u32;
-
-        canvas_clone.set_width(physical_width);
-        canvas_clone.set_height(physical_height);
-
-        if let Some(element) = canvas_clone.dyn_ref::<web_sys::HtmlElement>() {
-            let _ = element.style().set_property("width", &format!("{logical_width}px"));
-            let _ = element
-                .style()
-                .set_property("height", &format!("{logical_height}px"));
-        }
+    pub fn get_terrain_settings(&self) -> &TerrainSettings {
+        &self.terrain.settings
+    }
 
-        state.borrow_mut().resize(physical_width, physical_height);
-    }) as Box<dyn FnMut()>);
+    pub fn get_terrain_quality(&self) -> &TerrainQuality {
+        self.terrain.quality()
+    }
 
-    if let Some(window) = web_sys::window() {
-        let _ = window.add_event_listener_with_callback("resize", closure.as_ref().unchecked_ref());
+    /// Rebuild the chunk pool for a new quality; regenerates every chunk
+    pub fn set_terrain_quality(&mut self, quality: TerrainQuality) -> Result<(), String> {
+        self.terrain.set_quality(&self.gpu.device, quality)
     }
-    closure.forget();
-}
 
-fn start_frame_loop(state: Rc<RefCell<AppState>>) {
-    let f: Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>> = Rc::new(RefCell::new(None));
-    let g = f.clone();
+    pub fn get_shadow_settings(&self) -> &ShadowSettings {
+        self.terrain.shadow_settings()
+    }
 
-    *g.borrow_mut() = Some(Closure::new(move |timestamp: f64| {
-        {
-            let mut state = state.borrow_mut();
-            // update() returns dt, which render() needs for particle simulation
-            let dt = state.update(timestamp);
-            state.render(dt);
-        }
+    /// Toggle terrain shadows or change their resolution and cascades
+    pub fn update_shadow_settings(&mut self, settings: ShadowSettings) -> Result<(), String> {
+        self.terrain.update_shadow_settings(&self.gpu.device, settings)
+    }
 
-        // Request next frame
-        if let Some(window) = web_sys::window() {
-            if let Some(closure) = f.borrow().as_ref() {
-                let _ = window.request_animation_frame(closure.as_ref().unchecked_ref());
-            }
-        }
-    }));
+    /// Min/max terrain height of the chunk under a world XZ position, if known yet
+    pub fn terrain_height_bounds_at(&self, x: f32, z: f32) -> Option<HeightBounds> {
+        self.terrain.height_bounds_at(x, z)
+    }
 
-    if let Some(window) = web_sys::window() {
-        if let Some(closure) = g.borrow().as_ref() {
-            let _ = window.request_animation_frame(closure.as_ref().unchecked_ref());
-        }
+    /// CPU sampler for the current terrain settings. Erosion isn't included.
+    pub fn terrain_sampler(&self) -> TerrainSampler {
+        TerrainSampler::new(&self.terrain.settings).with_quality(*self.terrain.quality())
     }
-}
 
-fn default_settings_to_js<T, F>(map_fn: F, label: &str) -> Result<JsValue, JsValue>
-where
-    T: Serialize + Default,
-    F: Fn(presets::FullPreset) -> T,
-{
-    let settings = presets::get_default_preset()
-        .map(map_fn)
-        .unwrap_or_default();
-    serde_wasm_bindgen::to_value(&settings)
-        .map_err(|e| JsValue::from_str(&format!("Failed to serialize {}: {}", label, e)))
-}
+    /// Terrain height at a world XZ position, from the heights generated on the GPU
+    /// (erosion included). None until the chunk under it has streamed in and been read back.
+    pub fn terrain_height_at(&self, x: f32, z: f32) -> Option<f32> {
+        self.terrain.height_at(x, z)
+    }
 
-/// Update terrain settings from JavaScript
-/// Called with a JS object containing settings fields
-#[wasm_bindgen]
-pub fn update_terrain_settings(settings_js: JsValue) -> Result<(), JsValue> {
-    let settings: TerrainSettings = serde_wasm_bindgen::from_value(settings_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to parse settings: {}", e)))?;
-    with_app_state_mut(|state| state.update_terrain_settings(settings))
-}
+    /// Terrain surface normal at a world XZ position, from central differences of
+    /// `terrain_height_at` over the chunk vertex spacing
+    pub fn terrain_normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        let step = self.terrain.quality().sample_spacing();
+        let dx = (self.terrain_height_at(x + step, z)? - self.terrain_height_at(x - step, z)?) / (2.0 * step);
+        let dz = (self.terrain_height_at(x, z + step)? - self.terrain_height_at(x, z - step)?) / (2.0 * step);
+        Some(Vec3::new(-dx, 1.0, -dz).normalize())
+    }
 
-/// Get current terrain settings as a JS object
-#[wasm_bindgen]
-pub fn get_terrain_settings() -> Result<JsValue, JsValue> {
-    let settings = with_app_state(|state| state.get_terrain_settings().clone())?;
-    serde_wasm_bindgen::to_value(&settings)
-        .map_err(|e| JsValue::from_str(&format!("Failed to serialize settings: {}", e)))
-}
+    /// First terrain point hit by a ray, searched up to the view radius (which stays
+    /// within the loaded chunks). Terrain whose heights haven't been read back yet
+    /// is skipped, so the ray can hit loaded terrain behind it.
+    pub fn raycast_terrain(&self, origin: Vec3, dir: Vec3) -> Option<Vec3> {
+        let quality = self.terrain.quality();
+        let max_distance = quality.view_radius as f32 * quality.chunk_world_size;
+        raycast_heightfield(origin, dir, max_distance, quality.sample_spacing(), |x, z| {
+            self.terrain_height_at(x, z)
+        })
+    }
 
-/// Regenerate terrain with current settings (called from JS on R key press)
-#[wasm_bindgen]
-pub fn regenerate_terrain() -> Result<(), JsValue> {
-    with_app_state_mut(|state| state.queue_terrain_regeneration())
-}
+    pub fn queue_terrain_regeneration(&mut self) {
+        self.terrain.queue_regeneration();
+    }
 
-/// Update sky settings from JavaScript
-#[wasm_bindgen]
-pub fn update_sky_settings(settings_js: JsValue) -> Result<(), JsValue> {
-    let settings: SkySettings = serde_wasm_bindgen::from_value(settings_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to parse sky settings: {}", e)))?;
-    with_app_state_mut(|state| state.update_sky_settings(settings))
-}
+    /// Generate every chunk still waiting to stream in around the camera
+    pub fn finish_terrain_streaming(&mut self) {
+        self.terrain
+            .finish_streaming(&self.gpu.device, &self.gpu.queue, self.camera.active());
+    }
 
-/// Get current sky settings as a JS object
-#[wasm_bindgen]
-pub fn get_sky_settings() -> Result<JsValue, JsValue> {
-    let settings = with_app_state(|state| state.get_sky_settings().clone())?;
-    serde_wasm_bindgen::to_value(&settings)
-        .map_err(|e| JsValue::from_str(&format!("Failed to serialize sky settings: {}", e)))
-}
+    /// Read back the heights of every loaded chunk, blocking until they arrive
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn finish_terrain_height_readback(&mut self) {
+        self.terrain.finish_height_readback(&self.gpu.device, &self.gpu.queue);
+    }
 
-/// Update particle settings from JavaScript
-#[wasm_bindgen]
-pub fn update_particle_settings(settings_js: JsValue) -> Result<(), JsValue> {
-    let settings: ParticleSettings = serde_wasm_bindgen::from_value(settings_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to parse particle settings: {}", e)))?;
-    with_app_state_mut(|state| state.update_particle_settings(settings))
-}
+    pub fn get_day_cycle_settings(&self) -> &DayCycleSettings {
+        &self.day_cycle.settings
+    }
 
-/// Get current particle settings as a JS object
-#[wasm_bindgen]
-pub fn get_particle_settings() -> Result<JsValue, JsValue> {
-    let settings = with_app_state(|state| state.get_particle_settings().clone())?;
-    serde_wasm_bindgen::to_value(&settings)
-        .map_err(|e| JsValue::from_str(&format!("Failed to serialize particle settings: {}", e)))
-}
+    pub fn update_day_cycle_settings(&mut self, settings: DayCycleSettings) {
+        self.day_cycle.settings = settings;
+        self.day_cycle.set_time_of_day(self.day_cycle.settings.time_of_day);
+    }
 
-/// Get default terrain settings (before app initialization)
-#[wasm_bindgen]
-pub fn get_default_terrain_settings() -> Result<JsValue, JsValue> {
-    default_settings_to_js(|p| p.terrain, "terrain defaults")
-}
+    /// Jump to a time of day in hours (12 is noon), enabling the day/night cycle
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        self.day_cycle.settings.enabled = true;
+        self.day_cycle.set_time_of_day(hours);
+    }
 
-/// Get default sky settings (before app initialization)
-#[wasm_bindgen]
-pub fn get_default_sky_settings() -> Result<JsValue, JsValue> {
-    default_settings_to_js(|p| p.sky, "sky defaults")
-}
+    pub fn update_sky_settings(&mut self, settings: SkySettings) {
+        self.sky.update_settings(settings);
+    }
 
-/// Get default particle settings (before app initialization)
-#[wasm_bindgen]
-pub fn get_default_particle_settings() -> Result<JsValue, JsValue> {
-    default_settings_to_js(|p| p.particles, "particle defaults")
-}
+    pub fn get_sky_settings(&self) -> &SkySettings {
+        &self.sky.settings
+    }
 
-/// Get list of available presets (id, name)
-#[wasm_bindgen]
-pub fn get_preset_list() -> Result<JsValue, JsValue> {
-    let list = presets::get_preset_list();
-    serde_wasm_bindgen::to_value(&list)
-        .map_err(|e| JsValue::from_str(&format!("Failed to serialize preset list: {}", e)))
-}
+    pub fn update_particle_settings(&mut self, settings: ParticleSettings) {
+        self.particles.update_settings(settings);
+    }
 
-/// Get a full preset by ID
-#[wasm_bindgen]
-pub fn get_preset(id: &str) -> Result<JsValue, JsValue> {
-    match presets::get_preset(id) {
-        Some(preset) => serde_wasm_bindgen::to_value(&preset)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize preset: {}", e))),
-        None => Err(JsValue::from_str(&format!("Unknown preset: {}", id))),
+    pub fn get_particle_settings(&self) -> &ParticleSettings {
+        &self.particles.settings
     }
 }
 
-/// Get the default preset ID
-#[wasm_bindgen]
-pub fn get_default_preset_id() -> String {
-    presets::get_default_preset_id().to_string()
-}
@@ -0,0 +1,398 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use glam::{Vec2, Vec3};
+use serde_json::json;
+
+use crate::erosion::erosion_border;
+use crate::sampler::TerrainSampler;
+use crate::terrain::{grid_indices, ChunkCoord, TerrainQuality, TerrainRenderer, TerrainSettings, HEIGHT_APRON};
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A; // "JSON"
+const GLB_CHUNK_BIN: u32 = 0x004E_4942; // "BIN\0"
+
+// glTF enums
+const COMPONENT_UNSIGNED_SHORT: u32 = 5123;
+const COMPONENT_FLOAT: u32 = 5126;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// One chunk baked into real geometry, positions relative to the chunk origin
+#[derive(Clone, Debug)]
+pub struct ChunkMesh {
+    pub coord: ChunkCoord,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub colors: Vec<[f32; 3]>,
+}
+
+impl ChunkMesh {
+    /// Bake from a grid laid out like `TerrainSampler::sample_grid` with `HEIGHT_APRON`;
+    /// the apron gives edge normals that match the vertex shader
+    fn build(heights: &[f32], quality: &TerrainQuality, settings: &TerrainSettings, coord: ChunkCoord) -> Self {
+        let size = quality.chunk_size as i32;
+        let grid_size = quality.height_grid_size() as i32;
+        let apron = HEIGHT_APRON as i32;
+        let height = |x: i32, y: i32| heights[((y + apron) * grid_size + x + apron) as usize];
+        let step = quality.sample_spacing();
+
+        let vertex_count = (size * size) as usize;
+        let mut positions = Vec::with_capacity(vertex_count);
+        let mut normals = Vec::with_capacity(vertex_count);
+        let mut colors = Vec::with_capacity(vertex_count);
+        for y in 0..size {
+            for x in 0..size {
+                let h = height(x, y);
+                let local_uv = Vec2::new(x as f32, y as f32) / (size - 1) as f32;
+                let local_xz = local_uv * quality.chunk_world_size;
+
+                let dx = (height(x + 1, y) - height(x - 1, y)) / (2.0 * step);
+                let dz = (height(x, y + 1) - height(x, y - 1)) / (2.0 * step);
+                let normal = Vec3::new(-dx, 1.0, -dz).normalize();
+
+                positions.push([local_xz.x, h, local_xz.y]);
+                normals.push(normal.to_array());
+                colors.push(settings.base_color(h, 1.0 - normal.y).to_array());
+            }
+        }
+
+        Self {
+            coord,
+            positions,
+            normals,
+            colors,
+        }
+    }
+
+    fn name(&self) -> String {
+        format!("chunk_{}_{}", self.coord.x, self.coord.z)
+    }
+}
+
+/// A region of chunks baked into meshes for DCC tools
+#[derive(Clone, Debug)]
+pub struct TerrainMesh {
+    pub quality: TerrainQuality,
+    pub chunks: Vec<ChunkMesh>,
+}
+
+impl TerrainMesh {
+    /// Bake an inclusive range of chunks on the CPU. Erosion only runs on the GPU,
+    /// so this fails when it's enabled; use `from_terrain`.
+    pub fn build(
+        settings: &TerrainSettings,
+        quality: &TerrainQuality,
+        min: ChunkCoord,
+        max: ChunkCoord,
+    ) -> Result<Self, String> {
+        if erosion_border(settings) > 0 {
+            return Err("Erosion only runs on the GPU; export eroded terrain with TerrainMesh::from_terrain".to_string());
+        }
+        let sampler = TerrainSampler::new(settings).with_quality(*quality);
+        Self::bake(settings, quality, min, max, |coord| Ok(sampler.sample_grid(coord, HEIGHT_APRON)))
+    }
+
+    /// Bake an inclusive range of loaded chunks from the heights generated on the GPU,
+    /// erosion included. Fails unless every chunk has streamed in and been read back
+    /// (see `TerrainRenderer::finish_height_readback`).
+    pub fn from_terrain(terrain: &TerrainRenderer, min: ChunkCoord, max: ChunkCoord) -> Result<Self, String> {
+        Self::bake(&terrain.settings, terrain.quality(), min, max, |coord| {
+            terrain
+                .chunk_heights(coord)
+                .map(<[f32]>::to_vec)
+                .ok_or_else(|| format!("Chunk {:?} heights haven't been read back yet", coord))
+        })
+    }
+
+    fn bake(
+        settings: &TerrainSettings,
+        quality: &TerrainQuality,
+        min: ChunkCoord,
+        max: ChunkCoord,
+        chunk_grid: impl Fn(ChunkCoord) -> Result<Vec<f32>, String>,
+    ) -> Result<Self, String> {
+        if max.x < min.x || max.z < min.z {
+            return Err(format!("Empty chunk range: {:?}..={:?}", min, max));
+        }
+
+        let mut chunks = Vec::new();
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let coord = ChunkCoord::new(x, z);
+                chunks.push(ChunkMesh::build(&chunk_grid(coord)?, quality, settings, coord));
+            }
+        }
+        Ok(Self {
+            quality: *quality,
+            chunks,
+        })
+    }
+
+    /// Encode as binary glTF 2.0 with one node per chunk, translated to its world offset
+    pub fn to_glb(&self) -> Result<Vec<u8>, String> {
+        let mut bin: Vec<u8> = Vec::new();
+        let mut buffer_views = Vec::new();
+        let mut accessors = Vec::new();
+        let mut meshes = Vec::new();
+        let mut nodes = Vec::new();
+
+        // All chunks share the same grid topology
+        let indices: Vec<u16> = grid_indices(self.quality.chunk_size, 1).iter().map(|&i| i as u16).collect();
+        push_view(&mut bin, &mut buffer_views, bytemuck::cast_slice(&indices), TARGET_ELEMENT_ARRAY_BUFFER);
+        accessors.push(json!({
+            "bufferView": 0,
+            "componentType": COMPONENT_UNSIGNED_SHORT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+
+        for chunk in &self.chunks {
+            let (min, max) = chunk.positions.iter().fold(
+                (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |(min, max), p| (min.min(Vec3::from(*p)), max.max(Vec3::from(*p))),
+            );
+
+            let mut attribute = |data: &[[f32; 3]], bounds: Option<(Vec3, Vec3)>| {
+                let view = push_view(&mut bin, &mut buffer_views, bytemuck::cast_slice(data), TARGET_ARRAY_BUFFER);
+                let mut accessor = json!({
+                    "bufferView": view,
+                    "componentType": COMPONENT_FLOAT,
+                    "count": data.len(),
+                    "type": "VEC3",
+                });
+                if let Some((min, max)) = bounds {
+                    accessor["min"] = json!(min.to_array());
+                    accessor["max"] = json!(max.to_array());
+                }
+                accessors.push(accessor);
+                accessors.len() - 1
+            };
+            let position = attribute(&chunk.positions, Some((min, max)));
+            let normal = attribute(&chunk.normals, None);
+            let color = attribute(&chunk.colors, None);
+
+            meshes.push(json!({
+                "name": chunk.name(),
+                "primitives": [{
+                    "attributes": { "POSITION": position, "NORMAL": normal, "COLOR_0": color },
+                    "indices": 0,
+                    "material": 0,
+                }],
+            }));
+            let offset = chunk.coord.world_offset(&self.quality);
+            nodes.push(json!({
+                "name": chunk.name(),
+                "mesh": meshes.len() - 1,
+                "translation": [offset[0], 0.0, offset[1]],
+            }));
+        }
+
+        let root = nodes.len();
+        nodes.push(json!({ "name": "terrain", "children": (0..root).collect::<Vec<_>>() }));
+
+        let document = json!({
+            "asset": { "version": "2.0", "generator": "procedural-terrain-simulator" },
+            "scene": 0,
+            "scenes": [{ "nodes": [root] }],
+            "nodes": nodes,
+            "meshes": meshes,
+            "materials": [{
+                "name": "terrain",
+                "pbrMetallicRoughness": { "baseColorFactor": [1.0, 1.0, 1.0, 1.0], "metallicFactor": 0.0, "roughnessFactor": 1.0 },
+            }],
+            "accessors": accessors,
+            "bufferViews": buffer_views,
+            "buffers": [{ "byteLength": bin.len() }],
+        });
+
+        let mut json_bytes = serde_json::to_vec(&document)
+            .map_err(|e| format!("Failed to serialize glTF JSON: {}", e))?;
+        // Chunks must be 4-byte aligned: JSON pads with spaces, BIN with zeros
+        json_bytes.resize(json_bytes.len().next_multiple_of(4), b' ');
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let total_len = 12 + 8 + json_bytes.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(total_len);
+        glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total_len as u32).to_le_bytes());
+        glb.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+        glb.extend_from_slice(&json_bytes);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+        glb.extend_from_slice(&bin);
+        Ok(glb)
+    }
+
+    /// Write as Wavefront OBJ in world space, with per-vertex colors ("v x y z r g b")
+    pub fn write_obj(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        let indices = grid_indices(self.quality.chunk_size, 1);
+        writeln!(writer, "# procedural-terrain-simulator")?;
+
+        let mut base = 1; // OBJ indices are 1-based
+        for chunk in &self.chunks {
+            let offset = chunk.coord.world_offset(&self.quality);
+            writeln!(writer, "o {}", chunk.name())?;
+            for (p, c) in chunk.positions.iter().zip(&chunk.colors) {
+                writeln!(
+                    writer,
+                    "v {} {} {} {} {} {}",
+                    p[0] + offset[0],
+                    p[1],
+                    p[2] + offset[1],
+                    c[0],
+                    c[1],
+                    c[2]
+                )?;
+            }
+            for n in &chunk.normals {
+                writeln!(writer, "vn {} {} {}", n[0], n[1], n[2])?;
+            }
+            for tri in indices.chunks(3) {
+                let (a, b, c) = (tri[0] + base, tri[1] + base, tri[2] + base);
+                writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+            }
+            base += chunk.positions.len() as u32;
+        }
+        Ok(())
+    }
+
+    /// Write to a .glb or .obj file, chosen by extension
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        let result = match ext.as_deref() {
+            Some("glb") => writer.write_all(&self.to_glb()?),
+            Some("obj") => self.write_obj(&mut writer),
+            _ => return Err(format!("Unsupported mesh format: {}", path.display())),
+        };
+        result
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Append a 4-byte aligned buffer view to the binary chunk, returning its index
+fn push_view(bin: &mut Vec<u8>, views: &mut Vec<serde_json::Value>, data: &[u8], target: u32) -> usize {
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    views.push(json!({
+        "buffer": 0,
+        "byteOffset": bin.len(),
+        "byteLength": data.len(),
+        "target": target,
+    }));
+    bin.extend_from_slice(data);
+    views.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh() -> TerrainMesh {
+        let quality = TerrainQuality::default();
+        TerrainMesh::build(&TerrainSettings::default(), &quality, ChunkCoord::new(0, 0), ChunkCoord::new(1, 0)).unwrap()
+    }
+
+    #[test]
+    fn glb_has_valid_layout() {
+        let glb = mesh().to_glb().unwrap();
+        let word = |i: usize| u32::from_le_bytes(glb[i..i + 4].try_into().unwrap());
+        assert_eq!(word(0), GLB_MAGIC);
+        assert_eq!(word(4), 2);
+        assert_eq!(word(8) as usize, glb.len());
+
+        let json_len = word(12) as usize;
+        assert_eq!(word(16), GLB_CHUNK_JSON);
+        let document: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        // Two chunk nodes plus the root
+        assert_eq!(document["nodes"].as_array().unwrap().len(), 3);
+        assert_eq!(document["nodes"][1]["translation"][0], 256.0);
+
+        let bin_len = word(20 + json_len) as usize;
+        assert_eq!(word(24 + json_len), GLB_CHUNK_BIN);
+        assert_eq!(document["buffers"][0]["byteLength"].as_u64().unwrap() as usize, bin_len);
+    }
+
+    #[test]
+    fn neighbouring_chunks_share_edges() {
+        let mesh = mesh();
+        let (left, right) = (&mesh.chunks[0], &mesh.chunks[1]);
+        let size = mesh.quality.chunk_size as usize;
+        for y in 0..size {
+            let i = y * size;
+            let (a, b) = (left.positions[i + size - 1], right.positions[i]);
+            assert_eq!(a[0], mesh.quality.chunk_world_size);
+            assert_eq!((a[1], a[2]), (b[1], b[2]));
+            // The apron gives both sides of the seam the same normal
+            assert_eq!(left.normals[i + size - 1], right.normals[i]);
+        }
+    }
+
+    #[test]
+    fn obj_references_every_vertex() {
+        let mut out = Vec::new();
+        mesh().write_obj(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let vertices = text.lines().filter(|l| l.starts_with("v ")).count();
+        let faces = text.lines().filter(|l| l.starts_with("f ")).count();
+        let size = TerrainQuality::default().chunk_size;
+        assert_eq!(vertices, 2 * (size * size) as usize);
+        assert_eq!(faces, 2 * grid_indices(size, 1).len() / 3);
+    }
+
+    #[test]
+    fn cpu_bake_refuses_eroded_terrain() {
+        let settings = TerrainSettings {
+            thermal_iterations: 2,
+            ..Default::default()
+        };
+        let coord = ChunkCoord::new(0, 0);
+        assert!(TerrainMesh::build(&settings, &TerrainQuality::default(), coord, coord).is_err());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn bakes_eroded_chunks_from_read_back_heights() {
+        use crate::camera::FlyCamera;
+        use crate::webgpu::test_gpu;
+
+        let gpu = test_gpu();
+        let settings = TerrainSettings {
+            erosion_iterations: 4,
+            ..Default::default()
+        };
+        let quality = TerrainQuality {
+            view_radius: 1,
+            ..Default::default()
+        };
+        let mut terrain = TerrainRenderer::new(&gpu.device, gpu.surface_format, settings, quality).unwrap();
+        let (min, max) = (ChunkCoord::new(0, 0), ChunkCoord::new(1, 0));
+        terrain.finish_streaming(&gpu.device, &gpu.queue, &FlyCamera::new(1.0));
+        assert!(TerrainMesh::from_terrain(&terrain, min, max).is_err(), "heights aren't read back yet");
+        terrain.finish_height_readback(&gpu.device, &gpu.queue);
+
+        let mesh = TerrainMesh::from_terrain(&terrain, min, max).unwrap();
+        let size = quality.chunk_size as usize;
+        for chunk in &mesh.chunks {
+            let [x, z] = chunk.coord.world_offset(&quality);
+            let p = chunk.positions[3 * size + 5];
+            let expected = terrain.height_at(x + p[0], z + p[2]).unwrap();
+            assert!((p[1] - expected).abs() < 1e-3);
+        }
+        // Normals at the seam come from the apron, so both sides agree
+        let (left, right) = (&mesh.chunks[0], &mesh.chunks[1]);
+        for y in 0..size {
+            let (a, b) = (left.normals[y * size + size - 1], right.normals[y * size]);
+            assert!(Vec3::from(a).abs_diff_eq(Vec3::from(b), 1e-3));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use glam::Vec3;
+
+use crate::presets::FullPreset;
+use crate::webgpu::GpuState;
+use crate::AppState;
+
+/// Camera placement for an offline frame
+#[derive(Clone, Copy, Debug)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub yaw: f32,   // Horizontal rotation (radians)
+    pub pitch: f32, // Vertical rotation (radians)
+}
+
+/// Renders single frames without a canvas and reads them back to the CPU.
+/// Used for thumbnails and golden-image tests.
+pub struct OfflineRenderer {
+    app: AppState,
+}
+
+impl OfflineRenderer {
+    pub async fn new(width: u32, height: u32, preset: FullPreset, seed: u32) -> Result<Self, String> {
+        let gpu = GpuState::new_headless(width, height).await?;
+        let app = AppState::from_preset(gpu, preset, seed)?;
+        Ok(Self { app })
+    }
+
+    /// Access the app state to adjust settings between frames
+    pub fn app_mut(&mut self) -> &mut AppState {
+        &mut self.app
+    }
+
+    /// Render one frame from the given pose and return it as tightly packed RGBA8 (sRGB)
+    pub fn render_frame(&mut self, pose: CameraPose) -> Result<Vec<u8>, String> {
+        let camera = self.app.camera_mut();
+        camera.position = pose.position;
+        camera.yaw = pose.yaw;
+        camera.pitch = pose.pitch;
+
+        // A zero timestamp always yields the default 16ms step
+        let dt = self.app.update(0.0);
+        // Offline frames can't wait for chunks to stream in over later frames
+        self.app.finish_terrain_streaming();
+        self.app.render(dt);
+
+        self.read_back()
+    }
+
+    /// Render one frame from the given pose and write it to a PNG file
+    pub fn save_png(&mut self, pose: CameraPose, path: impl AsRef<Path>) -> Result<(), String> {
+        let pixels = self.render_frame(pose)?;
+        let config = &self.app.gpu().config;
+        write_png(path.as_ref(), config.width, config.height, &pixels)
+    }
+
+    fn read_back(&self) -> Result<Vec<u8>, String> {
+        let gpu = self.app.gpu();
+        let texture = gpu
+            .offscreen_texture()
+            .ok_or("Offline renderer has no offscreen color target")?;
+        let width = gpu.config.width;
+        let height = gpu.config.height;
+
+        // Texture copies require rows padded to COPY_BYTES_PER_ROW_ALIGNMENT
+        let unpadded_row = width * 4;
+        let padded_row = unpadded_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Readback Buffer"),
+            size: (padded_row * height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Frame Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        gpu.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| format!("Frame readback was dropped: {}", e))?
+            .map_err(|e| format!("Failed to map frame readback buffer: {:?}", e))?;
+
+        let mapped = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((unpadded_row * height) as usize);
+        for row in mapped.chunks_exact(padded_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_row as usize]);
+        }
+        drop(mapped);
+        readback_buffer.unmap();
+
+        Ok(pixels)
+    }
+}
+
+/// Write tightly packed RGBA8 pixels to a PNG file
+pub fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    let file = File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+    writer
+        .write_image_data(pixels)
+        .map_err(|e| format!("Failed to write PNG data: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets;
+
+    fn pose() -> CameraPose {
+        CameraPose {
+            position: Vec3::new(0.0, 180.0, 0.0),
+            yaw: 0.6,
+            pitch: -0.3,
+        }
+    }
+
+    fn natural_renderer() -> OfflineRenderer {
+        let preset = presets::get_preset("natural").unwrap();
+        pollster::block_on(OfflineRenderer::new(160, 90, preset, 42)).expect("GPU tests need a hardware or software adapter")
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn renders_reproducible_frames_to_png() {
+        let mut renderer = natural_renderer();
+
+        let first = renderer.render_frame(pose()).unwrap();
+        assert_eq!(first.len(), 160 * 90 * 4);
+        // The frame should contain both terrain and sky, not a single clear color
+        assert!(first.chunks(4).any(|p| p != &first[..4]));
+
+        // A fresh renderer with the same inputs must produce the same image
+        let second = natural_renderer().render_frame(pose()).unwrap();
+        assert_eq!(first, second);
+
+        let path = std::env::temp_dir().join("offline_renderer_test.png");
+        renderer.save_png(pose(), &path).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (160, 90));
+        let _ = std::fs::remove_file(path);
+    }
+}
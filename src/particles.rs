@@ -526,7 +526,7 @@ impl ParticleSystem {
             compute_pass.set_pipeline(&self.compute_pipeline);
             compute_pass.set_bind_group(0, &self.compute_bind_groups[self.current_buffer], &[]);
 
-            let workgroups = (self.active_particle_count + PARTICLE_WORKGROUP_SIZE - 1) / PARTICLE_WORKGROUP_SIZE;
+            let workgroups = self.active_particle_count.div_ceil(PARTICLE_WORKGROUP_SIZE);
             compute_pass.dispatch_workgroups(workgroups, 1, 1);
         }
 
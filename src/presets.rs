@@ -2,7 +2,12 @@ use serde::{Deserialize, Serialize};
 
 use crate::particles::ParticleSettings;
 use crate::sky::{SkySettings, DEFAULT_MOON_PARALLAX};
-use crate::terrain::{TerrainSettings, DEFAULT_FOG_START, DEFAULT_FOG_DISTANCE};
+use crate::terrain::{
+    TerrainSettings, DEFAULT_AO_RADIUS, DEFAULT_AO_STRENGTH, DEFAULT_EROSION_EVAPORATION, DEFAULT_EROSION_RAIN,
+    DEFAULT_EROSION_SEDIMENT_CAPACITY, DEFAULT_FOG_DISTANCE, DEFAULT_FOG_START,
+    DEFAULT_FOAM_WIDTH, DEFAULT_SUN_DIRECTION, DEFAULT_THERMAL_STRENGTH, DEFAULT_THERMAL_TALUS_ANGLE,
+    DEFAULT_WATER_CLARITY, DEFAULT_WAVE_SPEED, DEFAULT_WAVE_STRENGTH,
+};
 
 // Preset-specific ambient value (differs from TerrainSettings::default() which uses 0.25)
 const PRESET_AMBIENT: f32 = 0.35;
@@ -103,9 +108,28 @@ fn natural_preset() -> FullPreset {
             roughness: 0.84,
             pattern_type: 2, // Islands pattern
             seed: 0,
+            erosion_iterations: 0,
+            erosion_rain: DEFAULT_EROSION_RAIN,
+            erosion_sediment_capacity: DEFAULT_EROSION_SEDIMENT_CAPACITY,
+            erosion_evaporation: DEFAULT_EROSION_EVAPORATION,
+            thermal_iterations: 0,
+            thermal_talus_angle: DEFAULT_THERMAL_TALUS_ANGLE,
+            thermal_strength: DEFAULT_THERMAL_STRENGTH,
             ambient: PRESET_AMBIENT,
             fog_start: DEFAULT_FOG_START,
             fog_distance: DEFAULT_FOG_DISTANCE,
+            sun_direction: DEFAULT_SUN_DIRECTION,
+            sun_color: [1.0, 1.0, 1.0],
+            sun_intensity: 1.0,
+            sun_from_sky: true,
+            ao_strength: DEFAULT_AO_STRENGTH,
+            ao_radius: DEFAULT_AO_RADIUS,
+            water_enabled: true, // Clear tropical water
+            sea_level: 0.0,
+            wave_strength: DEFAULT_WAVE_STRENGTH,
+            wave_speed: DEFAULT_WAVE_SPEED,
+            water_clarity: 40.0,
+            foam_width: DEFAULT_FOAM_WIDTH,
             // Natural colors
             color_abyss: [0.05, 0.1, 0.25],
             color_deep_water: [0.1, 0.2, 0.4],
@@ -152,9 +176,28 @@ fn desert_preset() -> FullPreset {
             roughness: 0.50,
             pattern_type: 3, // Valleys
             seed: 0,
+            erosion_iterations: 0,
+            erosion_rain: DEFAULT_EROSION_RAIN,
+            erosion_sediment_capacity: DEFAULT_EROSION_SEDIMENT_CAPACITY,
+            erosion_evaporation: DEFAULT_EROSION_EVAPORATION,
+            thermal_iterations: 0,
+            thermal_talus_angle: DEFAULT_THERMAL_TALUS_ANGLE,
+            thermal_strength: DEFAULT_THERMAL_STRENGTH,
             ambient: PRESET_AMBIENT,
             fog_start: DEFAULT_FOG_START,
             fog_distance: DEFAULT_FOG_DISTANCE,
+            sun_direction: DEFAULT_SUN_DIRECTION,
+            sun_color: [1.0, 1.0, 1.0],
+            sun_intensity: 1.0,
+            sun_from_sky: true,
+            ao_strength: DEFAULT_AO_STRENGTH,
+            ao_radius: DEFAULT_AO_RADIUS,
+            water_enabled: false, // Dry basins use the water bands as ground colors
+            sea_level: 0.0,
+            wave_strength: DEFAULT_WAVE_STRENGTH,
+            wave_speed: DEFAULT_WAVE_SPEED,
+            water_clarity: DEFAULT_WATER_CLARITY,
+            foam_width: DEFAULT_FOAM_WIDTH,
             // Desert theme colors
             color_abyss: [0.08, 0.05, 0.02],
             color_deep_water: [0.15, 0.1, 0.05],
@@ -200,9 +243,28 @@ fn lava_preset() -> FullPreset {
             roughness: 0.22,
             pattern_type: 1, // Ridged
             seed: 0,
+            erosion_iterations: 0,
+            erosion_rain: DEFAULT_EROSION_RAIN,
+            erosion_sediment_capacity: DEFAULT_EROSION_SEDIMENT_CAPACITY,
+            erosion_evaporation: DEFAULT_EROSION_EVAPORATION,
+            thermal_iterations: 0,
+            thermal_talus_angle: DEFAULT_THERMAL_TALUS_ANGLE,
+            thermal_strength: DEFAULT_THERMAL_STRENGTH,
             ambient: PRESET_AMBIENT,
             fog_start: DEFAULT_FOG_START,
             fog_distance: DEFAULT_FOG_DISTANCE,
+            sun_direction: DEFAULT_SUN_DIRECTION,
+            sun_color: [1.0, 1.0, 1.0],
+            sun_intensity: 1.0,
+            sun_from_sky: true,
+            ao_strength: DEFAULT_AO_STRENGTH,
+            ao_radius: DEFAULT_AO_RADIUS,
+            water_enabled: false, // The water bands are lava here
+            sea_level: 0.0,
+            wave_strength: DEFAULT_WAVE_STRENGTH,
+            wave_speed: DEFAULT_WAVE_SPEED,
+            water_clarity: DEFAULT_WATER_CLARITY,
+            foam_width: DEFAULT_FOAM_WIDTH,
             // Volcanic theme colors
             color_abyss: [0.05, 0.0, 0.0],
             color_deep_water: [0.2, 0.02, 0.0],
@@ -248,9 +310,28 @@ fn arctic_preset() -> FullPreset {
             roughness: 0.22,
             pattern_type: 3, // Valleys
             seed: 0,
+            erosion_iterations: 0,
+            erosion_rain: DEFAULT_EROSION_RAIN,
+            erosion_sediment_capacity: DEFAULT_EROSION_SEDIMENT_CAPACITY,
+            erosion_evaporation: DEFAULT_EROSION_EVAPORATION,
+            thermal_iterations: 0,
+            thermal_talus_angle: DEFAULT_THERMAL_TALUS_ANGLE,
+            thermal_strength: DEFAULT_THERMAL_STRENGTH,
             ambient: PRESET_AMBIENT,
             fog_start: DEFAULT_FOG_START,
             fog_distance: DEFAULT_FOG_DISTANCE,
+            sun_direction: DEFAULT_SUN_DIRECTION,
+            sun_color: [1.0, 1.0, 1.0],
+            sun_intensity: 1.0,
+            sun_from_sky: true,
+            ao_strength: DEFAULT_AO_STRENGTH,
+            ao_radius: DEFAULT_AO_RADIUS,
+            water_enabled: true, // Calm, dark polar sea
+            sea_level: 0.0,
+            wave_strength: 0.08,
+            wave_speed: DEFAULT_WAVE_SPEED,
+            water_clarity: 20.0,
+            foam_width: DEFAULT_FOAM_WIDTH,
             // Arctic colors
             color_abyss: [0.02, 0.08, 0.15],
             color_deep_water: [0.05, 0.15, 0.25],
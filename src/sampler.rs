@@ -0,0 +1,599 @@
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::terrain::{ChunkCoord, TerrainQuality, TerrainSettings};
+
+/// Maximum expected difference between CPU and GPU heights, relative to `height_scale`.
+/// The GPU may fuse multiply-adds and uses approximate `pow`, so results are not
+/// bit-identical. Terraced (4) is the exception: a sample sitting right on a step
+/// boundary can round to the neighbouring terrace, differing by one step. Blocky (5)
+/// is only approximate, see [`BLOCKY_GPU_TOLERANCE`].
+pub const GPU_HEIGHT_TOLERANCE: f32 = 1.0e-3;
+
+/// Maximum expected per-sample difference for the Blocky pattern, relative to
+/// `height_scale`. Its noise is sampled around `BLOCKY_OFFSET`, where an f32 only
+/// resolves steps of 0.06-0.125, so the simplex gradients depend on how each GPU rounds
+/// and fuses the lattice math. Evaluating in f64 does not help: it computes the exact
+/// noise, which the GPU does not. The CPU heights keep the overall shape of the terrain,
+/// not its exact samples.
+pub const BLOCKY_GPU_TOLERANCE: f32 = 0.75;
+
+/// Noise offset of the Blocky pattern; must match blocky_offset in terrain.wgsl.
+const BLOCKY_OFFSET: Vec2 = Vec2::new(1000000.0, 1337000.0);
+
+const RAYCAST_BISECTIONS: u32 = 16; // Refinement steps once a ray crosses the surface
+
+// ============================================
+// NOISE FUNCTIONS (Simplex 2D)
+// Straight ports of the WGSL versions - keep operation order identical
+// ============================================
+
+/// WGSL `fract` (floor-based, unlike `f32::fract` which truncates)
+fn fract3(x: Vec3) -> Vec3 {
+    x - x.floor()
+}
+
+/// WGSL `smoothstep`
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn mod289_2(x: Vec2) -> Vec2 {
+    x - (x * (1.0 / 289.0)).floor() * 289.0
+}
+
+fn mod289_3(x: Vec3) -> Vec3 {
+    x - (x * (1.0 / 289.0)).floor() * 289.0
+}
+
+fn permute3(x: Vec3) -> Vec3 {
+    mod289_3(((x * 34.0) + 1.0) * x)
+}
+
+// Constants are copied verbatim from the shader
+#[allow(clippy::excessive_precision)]
+pub fn simplex_noise_2d(v: Vec2) -> f32 {
+    let c = Vec4::new(
+        0.211324865405187,  // (3.0 - sqrt(3.0)) / 6.0
+        0.366025403784439,  // 0.5 * (sqrt(3.0) - 1.0)
+        -0.577350269189626, // -1.0 + 2.0 * C.x
+        0.024390243902439,  // 1.0 / 41.0
+    );
+
+    let mut i = (v + v.dot(Vec2::splat(c.y))).floor();
+    let x0 = v - i + i.dot(Vec2::splat(c.x));
+    let i1 = if x0.x > x0.y {
+        Vec2::new(1.0, 0.0)
+    } else {
+        Vec2::new(0.0, 1.0)
+    };
+    let mut x12 = Vec4::new(x0.x, x0.y, x0.x, x0.y) + Vec4::new(c.x, c.x, c.z, c.z);
+    x12 = Vec4::new(x12.x - i1.x, x12.y - i1.y, x12.z, x12.w);
+
+    i = mod289_2(i);
+    let p = permute3(
+        permute3(i.y + Vec3::new(0.0, i1.y, 1.0)) + i.x + Vec3::new(0.0, i1.x, 1.0),
+    );
+    let mut m = (Vec3::splat(0.5)
+        - Vec3::new(
+            x0.dot(x0),
+            Vec2::new(x12.x, x12.y).dot(Vec2::new(x12.x, x12.y)),
+            Vec2::new(x12.z, x12.w).dot(Vec2::new(x12.z, x12.w)),
+        ))
+    .max(Vec3::ZERO);
+    m *= m;
+    m *= m;
+
+    let x = 2.0 * fract3(p * c.w) - 1.0;
+    let h = x.abs() - 0.5;
+    let ox = (x + 0.5).floor();
+    let a0 = x - ox;
+
+    m *= 1.79284291400159 - 0.85373472095314 * (a0 * a0 + h * h);
+    let g = Vec3::new(
+        a0.x * x0.x + h.x * x0.y,
+        a0.y * x12.x + h.y * x12.y,
+        a0.z * x12.z + h.z * x12.w,
+    );
+    130.0 * m.dot(g)
+}
+
+/// Fractal Brownian Motion with a fixed 0.5 persistence
+fn fbm(p: Vec2, octaves: i32) -> f32 {
+    fbm_rough(p, octaves, 0.5)
+}
+
+/// FBM with configurable roughness (persistence)
+pub fn fbm_rough(p: Vec2, octaves: i32, roughness: f32) -> f32 {
+    let mut value = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+
+    for _ in 0..octaves {
+        value += amplitude * simplex_noise_2d(p * frequency);
+        amplitude *= roughness;
+        frequency *= 2.0;
+    }
+    value
+}
+
+/// Domain warping for more interesting terrain
+pub fn domain_warp(p: Vec2, warp_strength: f32) -> Vec2 {
+    let offset = Vec2::new(
+        fbm(p + Vec2::new(0.0, 0.0), 3),
+        fbm(p + Vec2::new(5.2, 1.3), 3),
+    );
+    p + offset * warp_strength
+}
+
+/// Island falloff - creates ocean around terrain
+pub fn island_falloff(world_pos: Vec2, center: Vec2, radius: f32) -> f32 {
+    let dist = (world_pos - center).length() / radius;
+    1.0 - smoothstep(0.3, 1.0, dist)
+}
+
+// ============================================
+// HEIGHT SAMPLING
+// ============================================
+
+/// First point where a ray hits a heightfield within `max_distance`, if any.
+/// Marches `step` at a time, then bisects the crossing. Positions where
+/// `height_at` returns None (heights not known yet) are skipped, so the hit
+/// is always on known terrain.
+pub(crate) fn raycast_heightfield(
+    origin: Vec3,
+    dir: Vec3,
+    max_distance: f32,
+    step: f32,
+    height_at: impl Fn(f32, f32) -> Option<f32>,
+) -> Option<Vec3> {
+    let dir = dir.try_normalize()?;
+    let below = |t: f32| {
+        let p = origin + dir * t;
+        height_at(p.x, p.z).is_some_and(|h| p.y <= h)
+    };
+    if below(0.0) {
+        return Some(origin);
+    }
+
+    let mut near = 0.0;
+    while near < max_distance {
+        let far = (near + step).min(max_distance);
+        if below(far) {
+            // Unknown heights count as above the surface, like the samples before them
+            let (mut lo, mut hi) = (near, far);
+            for _ in 0..RAYCAST_BISECTIONS {
+                let mid = 0.5 * (lo + hi);
+                if below(mid) {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            let hit = origin + dir * hi;
+            return Some(Vec3::new(hit.x, height_at(hit.x, hit.z)?, hit.z));
+        }
+        near = far;
+    }
+    None
+}
+
+/// CPU reference implementation of the `compute_height` compute shader.
+/// Lets tools query "what is the height at (x, z)?" without a GPU.
+/// Post-processing stages such as erosion are not included.
+#[derive(Clone, Debug)]
+pub struct TerrainSampler {
+    terrain_scale: f32,
+    height_scale: f32,
+    octaves: i32,
+    warp_strength: f32,
+    height_variance: f32,
+    roughness: f32,
+    pattern_type: u32,
+    seed_offset: Vec2,
+    quality: TerrainQuality,
+}
+
+impl TerrainSampler {
+    pub fn new(settings: &TerrainSettings) -> Self {
+        let seed = settings.seed as f32;
+        Self {
+            terrain_scale: settings.terrain_scale,
+            height_scale: settings.height_scale,
+            octaves: settings.octaves as i32,
+            warp_strength: settings.warp_strength,
+            height_variance: settings.height_variance,
+            roughness: settings.roughness,
+            pattern_type: settings.pattern_type,
+            // Matches the seed offset in compute_height
+            seed_offset: Vec2::new(seed * 0.1, seed * 0.137),
+            quality: TerrainQuality::default(),
+        }
+    }
+
+    /// Use a non-default chunk layout for `sample_chunk` and `sample_grid`
+    pub fn with_quality(mut self, quality: TerrainQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    pub fn quality(&self) -> &TerrainQuality {
+        &self.quality
+    }
+
+    /// Terrain height at a world-space (x, z) position
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        self.height_at_world(Vec2::new(x, z))
+    }
+
+    /// Surface normal at a world-space (x, z) position, from central differences
+    /// over the chunk vertex spacing like the terrain vertex shader
+    pub fn normal_at(&self, x: f32, z: f32) -> Vec3 {
+        let step = self.quality.sample_spacing();
+        let dx = (self.height_at(x + step, z) - self.height_at(x - step, z)) / (2.0 * step);
+        let dz = (self.height_at(x, z + step) - self.height_at(x, z - step)) / (2.0 * step);
+        Vec3::new(-dx, 1.0, -dz).normalize()
+    }
+
+    /// First point where a ray hits the terrain within `max_distance`, if any
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<Vec3> {
+        let step = self.quality.sample_spacing();
+        raycast_heightfield(origin, dir, max_distance, step, |x, z| Some(self.height_at(x, z)))
+    }
+
+    /// Heights for a whole chunk, `chunk_size` samples per row
+    pub fn sample_chunk(&self, coord: ChunkCoord) -> Vec<f32> {
+        self.sample_grid(coord, 0)
+    }
+
+    /// Heights for a chunk plus `border` extra samples on each side,
+    /// laid out like the output of `compute_height`
+    pub fn sample_grid(&self, coord: ChunkCoord, border: u32) -> Vec<f32> {
+        let [origin_x, origin_z] = coord.sample_origin(&self.quality);
+        let step = self.quality.sample_spacing();
+        let size = (self.quality.chunk_size + 2 * border) as i32;
+        let border = border as i32;
+        let mut heights = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let sample = Vec2::new((origin_x + x - border) as f32, (origin_z + y - border) as f32);
+                heights.push(self.height_at_world(sample * step));
+            }
+        }
+        heights
+    }
+
+    fn height_at_world(&self, world_pos: Vec2) -> f32 {
+        let noise_pos = (world_pos + self.seed_offset) * self.terrain_scale;
+
+        let octaves = self.octaves;
+        let roughness = self.roughness;
+        let variance = self.height_variance;
+        let height_scale = self.height_scale;
+
+        match self.pattern_type {
+            0 => {
+                // Standard terrain with domain warping
+                let warped_pos = domain_warp(noise_pos * 0.5, self.warp_strength);
+                let mut height = fbm_rough(warped_pos * 0.3, octaves, roughness) * height_scale;
+
+                let detail_octaves = (octaves - 2).max(2);
+                height += fbm_rough(noise_pos * 2.0, detail_octaves, roughness) * height_scale * 0.15 * variance;
+
+                let ridge = 1.0 - fbm_rough(noise_pos * 0.8, detail_octaves, roughness).abs();
+                height += ridge * ridge * height_scale * 0.3 * variance;
+                height
+            }
+            1 => {
+                // Ridged/mountainous
+                let warped_pos = domain_warp(noise_pos * 0.5, self.warp_strength * 1.5);
+                let mut height = fbm_rough(warped_pos * 0.2, octaves, roughness) * height_scale * 0.3;
+
+                let ridge1 = 1.0 - fbm_rough(warped_pos * 0.6, octaves, roughness).abs();
+                let ridge2 = 1.0 - fbm_rough(warped_pos * 1.0, octaves - 1, roughness).abs();
+
+                height += ridge1.powf(2.5) * height_scale * 0.6 * variance;
+                height += ridge2.powf(2.0) * height_scale * 0.3 * variance;
+
+                height += fbm_rough(noise_pos * 3.0, 4, roughness) * height_scale * 0.08;
+                height
+            }
+            2 => {
+                // Islands/archipelago
+                let warped_pos = domain_warp(noise_pos * 0.5, self.warp_strength);
+                let base_height = fbm_rough(warped_pos * 0.3, octaves, roughness);
+
+                let island_noise = fbm_rough(noise_pos * 0.15, 4, 0.5);
+                let island_mask = smoothstep(-0.1, 0.3, island_noise);
+
+                let mut height = (base_height * island_mask - 0.3) * height_scale;
+                height += fbm_rough(noise_pos * 2.0, 4, roughness) * height_scale * 0.1 * variance * island_mask;
+                height
+            }
+            3 => {
+                // Valleys/canyons - inverted ridges
+                let warped_pos = domain_warp(noise_pos * 0.5, self.warp_strength);
+                let mut height = fbm_rough(warped_pos * 0.3, octaves, roughness) * height_scale * 0.5;
+
+                let valley1 = fbm_rough(warped_pos * 0.5, octaves - 1, roughness).abs();
+                let valley2 = fbm_rough(warped_pos * 0.8 + Vec2::new(100.0, 100.0), octaves - 2, roughness).abs();
+
+                height -= (1.0 - valley1).powf(3.0) * height_scale * 0.6 * variance;
+                height -= (1.0 - valley2).powf(2.0) * height_scale * 0.3 * variance;
+
+                height += fbm_rough(noise_pos * 3.0, 3, roughness) * height_scale * 0.05;
+                height
+            }
+            4 => {
+                // Terraced/plateaus
+                let warped_pos = domain_warp(noise_pos * 0.5, self.warp_strength);
+                let raw_height = fbm_rough(warped_pos * 0.3, octaves, roughness) * height_scale;
+
+                let terrace_count = 6.0 + variance * 6.0;
+                let step_size = height_scale / terrace_count;
+                let mut height = (raw_height / step_size + 0.5).floor() * step_size;
+
+                height += fbm_rough(noise_pos * 4.0, 3, roughness) * step_size * 0.2;
+                height
+            }
+            5 => {
+                // Blocky/plateau terrain - distant noise region, only approximates the GPU
+                // because f32 precision is exhausted out there (see BLOCKY_GPU_TOLERANCE)
+                let blocky_pos = noise_pos + BLOCKY_OFFSET;
+                let warped_pos = domain_warp(blocky_pos * 0.5, self.warp_strength);
+                let mut height = fbm_rough(warped_pos * 0.3, octaves, roughness) * height_scale;
+
+                let detail_octaves = (octaves - 2).max(2);
+                height += fbm_rough(blocky_pos * 2.0, detail_octaves, roughness) * height_scale * 0.15 * variance;
+
+                let ridge = 1.0 - fbm_rough(blocky_pos * 0.8, detail_octaves, roughness).abs();
+                height += ridge * ridge * height_scale * 0.3 * variance;
+                height
+            }
+            _ => {
+                // Fallback to standard
+                let warped_pos = domain_warp(noise_pos * 0.5, self.warp_strength);
+                fbm_rough(warped_pos * 0.3, octaves, roughness) * height_scale
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::terrain::ComputeParams;
+    use crate::webgpu::test_gpu;
+    use wgpu::util::DeviceExt;
+
+    /// Runs `compute_height` for each chunk and reads the heights back.
+    fn gpu_chunk_heights(quality: &TerrainQuality, cases: &[(TerrainSettings, ChunkCoord)]) -> Vec<Vec<f32>> {
+        pollster::block_on(async {
+            let gpu = test_gpu();
+            let (device, queue) = (&gpu.device, &gpu.queue);
+
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Terrain Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/terrain.wgsl").into()),
+            });
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Height Compute Pipeline"),
+                layout: None,
+                module: &shader,
+                entry_point: Some("compute_height"),
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &quality.shader_constants(),
+                    ..Default::default()
+                },
+                cache: None,
+            });
+
+            let size = (quality.chunk_size * quality.chunk_size * 4) as u64;
+            let mut results = Vec::with_capacity(cases.len());
+            for (settings, coord) in cases {
+                let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Params Buffer"),
+                    contents: bytemuck::cast_slice(&[ComputeParams::new(settings, quality, *coord, 0)]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+                let height_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Height Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                });
+                let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Compute Bind Group"),
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: params_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: height_buffer.as_entire_binding(),
+                        },
+                    ],
+                });
+
+                let mut encoder = device.create_command_encoder(&Default::default());
+                {
+                    let mut pass = encoder.begin_compute_pass(&Default::default());
+                    pass.set_pipeline(&pipeline);
+                    pass.set_bind_group(0, &bind_group, &[]);
+                    let workgroups = quality.chunk_size.div_ceil(8);
+                    pass.dispatch_workgroups(workgroups, workgroups, 1);
+                }
+                encoder.copy_buffer_to_buffer(&height_buffer, 0, &readback_buffer, 0, size);
+                queue.submit(std::iter::once(encoder.finish()));
+
+                let slice = readback_buffer.slice(..);
+                slice.map_async(wgpu::MapMode::Read, |_| {});
+                device.poll(wgpu::Maintain::Wait);
+                results.push(bytemuck::cast_slice::<u8, f32>(&slice.get_mapped_range()).to_vec());
+            }
+            results
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn cpu_heights_match_gpu_for_all_patterns() {
+        let mut cases = Vec::new();
+        for pattern_type in 0..=5 {
+            for (seed, coord) in [(0, ChunkCoord::new(0, 0)), (424242, ChunkCoord::new(-3, 7))] {
+                let settings = TerrainSettings {
+                    pattern_type,
+                    seed,
+                    octaves: 4,
+                    ..Default::default()
+                };
+                cases.push((settings, coord));
+            }
+        }
+        // Preset settings cover large height scales and extreme warp/roughness values
+        for id in ["arctic", "chalk", "desert", "lava", "natural"] {
+            let mut settings = crate::presets::get_preset(id).unwrap().terrain;
+            settings.seed = 1234;
+            cases.push((settings, ChunkCoord::new(2, -5)));
+        }
+
+        let quality = TerrainQuality::default();
+        let gpu_results = gpu_chunk_heights(&quality, &cases);
+
+        for ((settings, coord), gpu) in cases.iter().zip(gpu_results) {
+            let cpu = TerrainSampler::new(settings).sample_chunk(*coord);
+            if settings.pattern_type == 5 {
+                // Per-sample Blocky heights are backend dependent; bound each sample and
+                // require the chunk to average out to the same height
+                let tolerance = BLOCKY_GPU_TOLERANCE * settings.height_scale;
+                for (i, (c, g)) in cpu.iter().zip(&gpu).enumerate() {
+                    assert!(
+                        (c - g).abs() <= tolerance,
+                        "blocky seed {} {:?} sample {}: cpu {} gpu {} (tolerance {})",
+                        settings.seed, coord, i, c, g, tolerance
+                    );
+                }
+                let cpu_mean = cpu.iter().sum::<f32>() / cpu.len() as f32;
+                let gpu_mean = gpu.iter().sum::<f32>() / gpu.len() as f32;
+                assert!(
+                    (cpu_mean - gpu_mean).abs() <= 0.1 * settings.height_scale,
+                    "blocky seed {} {:?}: cpu mean {} gpu mean {}",
+                    settings.seed, coord, cpu_mean, gpu_mean
+                );
+                continue;
+            }
+            let tolerance = GPU_HEIGHT_TOLERANCE * settings.height_scale;
+            let step_size = settings.height_scale / (6.0 + settings.height_variance * 6.0);
+
+            let mut terrace_flips = 0;
+            for (i, (c, g)) in cpu.iter().zip(&gpu).enumerate() {
+                let diff = (c - g).abs();
+                if diff <= tolerance {
+                    continue;
+                }
+                // Terrace quantization can flip at a step boundary (see GPU_HEIGHT_TOLERANCE)
+                if settings.pattern_type == 4 && (diff - step_size).abs() <= tolerance {
+                    terrace_flips += 1;
+                    continue;
+                }
+                panic!(
+                    "pattern {} seed {} {:?} sample {}: cpu {} gpu {} (tolerance {})",
+                    settings.pattern_type, settings.seed, coord, i, c, g, tolerance
+                );
+            }
+            assert!(
+                terrace_flips <= cpu.len() / 100,
+                "pattern {} seed {}: {} terrace samples differ",
+                settings.pattern_type,
+                settings.seed,
+                terrace_flips
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn cpu_heights_match_gpu_for_custom_quality() {
+        let quality = TerrainQuality {
+            chunk_size: 33,
+            chunk_world_size: 100.0,
+            view_radius: 4,
+        };
+        let settings = TerrainSettings {
+            pattern_type: 0,
+            ..Default::default()
+        };
+        let coord = ChunkCoord::new(-2, 5);
+        let gpu = gpu_chunk_heights(&quality, &[(settings.clone(), coord)]);
+
+        let cpu = TerrainSampler::new(&settings).with_quality(quality).sample_chunk(coord);
+        assert_eq!(cpu.len(), gpu[0].len());
+        let tolerance = GPU_HEIGHT_TOLERANCE * settings.height_scale;
+        for (c, g) in cpu.iter().zip(&gpu[0]) {
+            assert!((c - g).abs() <= tolerance, "cpu {} gpu {}", c, g);
+        }
+    }
+
+    #[test]
+    fn chunk_samples_match_point_queries() {
+        let settings = TerrainSettings::default();
+        let sampler = TerrainSampler::new(&settings);
+        let quality = *sampler.quality();
+        let coord = ChunkCoord::new(1, -2);
+        let heights = sampler.sample_chunk(coord);
+        let [x, z] = coord.world_offset(&quality);
+
+        // Chunk corners sit exactly on the chunk origin and far edge
+        assert_eq!(heights[0], sampler.height_at(x, z));
+        let last = (quality.chunk_size * quality.chunk_size - 1) as usize;
+        assert_eq!(
+            heights[last],
+            sampler.height_at(x + quality.chunk_world_size, z + quality.chunk_world_size)
+        );
+    }
+
+    #[test]
+    fn raycast_hits_the_surface() {
+        let sampler = TerrainSampler::new(&TerrainSettings::default());
+        let origin = Vec3::new(120.0, 500.0, -40.0);
+        let dir = Vec3::new(0.6, -0.5, 0.3);
+
+        let hit = sampler.raycast(origin, dir, 5000.0).expect("ray towards the ground should hit");
+        assert!((hit.y - sampler.height_at(hit.x, hit.z)).abs() < 1e-3);
+        // The hit lies on the ray
+        let along = (hit - origin).normalize();
+        assert!(along.dot(dir.normalize()) > 0.9999);
+
+        assert_eq!(sampler.raycast(origin, Vec3::Y, 5000.0), None);
+        assert!(sampler.normal_at(hit.x, hit.z).y > 0.0);
+    }
+
+    #[test]
+    fn raycast_skips_unknown_heights() {
+        // Flat ground with a wall at x = 50, unknown between x = 10 and 30
+        let height_at = |x: f32, _z: f32| match x {
+            x if (10.0..30.0).contains(&x) => None,
+            x if x >= 50.0 => Some(10.0),
+            _ => Some(0.0),
+        };
+
+        // Passing over the unknown strip, the ray hits the wall behind it
+        let hit = raycast_heightfield(Vec3::new(0.0, 5.0, 0.0), Vec3::X, 100.0, 1.0, height_at).unwrap();
+        assert!((hit.x - 50.0).abs() < 1e-3 && hit.y == 10.0, "{}", hit);
+
+        // A ray reaching the ground inside the strip hits where known terrain starts
+        let dir = Vec3::new(1.0, -0.5, 0.0);
+        let hit = raycast_heightfield(Vec3::new(0.0, 10.0, 0.0), dir, 100.0, 1.0, height_at).unwrap();
+        assert!((hit.x - 30.0).abs() < 1e-3 && hit.y == 0.0, "{}", hit);
+
+        assert_eq!(raycast_heightfield(Vec3::ZERO, -Vec3::Y, 100.0, 1.0, |_, _| None), None);
+    }
+}
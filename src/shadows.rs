@@ -0,0 +1,499 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+use wgpu::*;
+
+use crate::camera::{CameraController, CameraUniform};
+
+pub const DEFAULT_SHADOW_RESOLUTION: u32 = 2048;
+pub const DEFAULT_CASCADE_COUNT: u32 = 3;
+pub const DEFAULT_SHADOW_DISTANCE: f32 = 3000.0;
+pub const MAX_CASCADES: usize = 4; // Must match the light_view_proj array in terrain.wgsl
+const MIN_SHADOW_RESOLUTION: u32 = 256;
+const MAX_SHADOW_RESOLUTION: u32 = 4096;
+
+const SPLIT_LAMBDA: f32 = 0.8; // Blend of logarithmic (1) and uniform (0) cascade splits
+const CASTER_MARGIN: f32 = 3000.0; // Extra depth towards the light, for casters outside the view
+const SHADOW_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// Shadow settings that can be modified at runtime
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub resolution: u32,    // Texels per side of each cascade's shadow map
+    pub cascade_count: u32, // 1-4; more keeps distant shadows sharper
+    pub max_distance: f32,  // View depth covered by the last cascade
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: DEFAULT_SHADOW_RESOLUTION,
+            cascade_count: DEFAULT_CASCADE_COUNT,
+            max_distance: DEFAULT_SHADOW_DISTANCE,
+        }
+    }
+}
+
+impl ShadowSettings {
+    /// Reject values the shadow maps can't be built with
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_SHADOW_RESOLUTION..=MAX_SHADOW_RESOLUTION).contains(&self.resolution) {
+            return Err(format!(
+                "shadow resolution must be between {} and {}, got {}",
+                MIN_SHADOW_RESOLUTION, MAX_SHADOW_RESOLUTION, self.resolution
+            ));
+        }
+        if !(1..=MAX_CASCADES as u32).contains(&self.cascade_count) {
+            return Err(format!(
+                "cascade_count must be between 1 and {}, got {}",
+                MAX_CASCADES, self.cascade_count
+            ));
+        }
+        if !(self.max_distance.is_finite() && self.max_distance > 0.0) {
+            return Err(format!("shadow max_distance must be positive, got {}", self.max_distance));
+        }
+        Ok(())
+    }
+}
+
+/// One slice of the view frustum and the light projection that covers it
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowCascade {
+    pub view_proj: Mat4,
+    pub near_depth: f32,  // View depth where the cascade starts
+    pub split_depth: f32, // View depth where the next cascade takes over
+    pub texel_size: f32,  // World units per shadow map texel
+}
+
+/// Split the camera frustum (up to `max_distance`) into cascades and fit an
+/// orthographic light projection around each. Each cascade is bounded by a
+/// sphere and snapped to whole texels, so shadows don't shimmer as the camera
+/// turns or moves.
+pub fn fit_cascades(camera: &dyn CameraController, light_dir: Vec3, settings: &ShadowSettings) -> Vec<ShadowCascade> {
+    let eye = camera.position();
+    let forward = camera.view_matrix().inverse().transform_vector3(Vec3::NEG_Z).normalize();
+
+    // Rays through the frustum corners, from the near plane to the far plane
+    let inv_view_proj = camera.view_projection_matrix().inverse();
+    let corner = |x: f32, y: f32, z: f32| inv_view_proj.project_point3(Vec3::new(x, y, z));
+    let rays: Vec<(Vec3, Vec3)> = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .iter()
+        .map(|&(x, y)| (corner(x, y, 0.0), corner(x, y, 1.0)))
+        .collect();
+    let near = (rays[0].0 - eye).dot(forward);
+    let far = (rays[0].1 - eye).dot(forward);
+    let shadow_far = far.min(settings.max_distance);
+
+    let light_dir = light_dir.normalize_or(Vec3::Y);
+    let up = if light_dir.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+    let light_view = Mat4::look_to_rh(Vec3::ZERO, -light_dir, up);
+
+    let count = settings.cascade_count.clamp(1, MAX_CASCADES as u32);
+    let mut cascades = Vec::with_capacity(count as usize);
+    let mut start = near;
+    for i in 1..=count {
+        // Practical split scheme: mostly logarithmic, some uniform
+        let t = i as f32 / count as f32;
+        let log = near * (shadow_far / near).powf(t);
+        let uniform = near + (shadow_far - near) * t;
+        let end = SPLIT_LAMBDA * log + (1.0 - SPLIT_LAMBDA) * uniform;
+
+        let slice: Vec<Vec3> = rays
+            .iter()
+            .flat_map(|&(n, f)| {
+                [start, end].map(|depth| n.lerp(f, (depth - near) / (far - near)))
+            })
+            .collect();
+        let center = slice.iter().copied().sum::<Vec3>() / slice.len() as f32;
+        let radius = slice.iter().map(|p| p.distance(center)).fold(0.0, f32::max).ceil();
+
+        let texel_size = 2.0 * radius / settings.resolution as f32;
+        let c = light_view.transform_point3(center);
+        let x = (c.x / texel_size).floor() * texel_size;
+        let y = (c.y / texel_size).floor() * texel_size;
+        let distance = -c.z;
+        let proj = Mat4::orthographic_rh(
+            x - radius,
+            x + radius,
+            y - radius,
+            y + radius,
+            distance - radius - CASTER_MARGIN,
+            distance + radius,
+        );
+
+        cascades.push(ShadowCascade {
+            view_proj: proj * light_view,
+            near_depth: start,
+            split_depth: end,
+            texel_size,
+        });
+        start = end;
+    }
+    cascades
+}
+
+/// Shadow sampling uniforms - must match ShadowParams in terrain.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ShadowParams {
+    light_view_proj: [[[f32; 4]; 4]; MAX_CASCADES],
+    split_depths: [f32; 4],   // Far view depth of each cascade
+    texel_sizes: [f32; 4],    // World units per texel of each cascade
+    camera_forward: [f32; 4], // w unused
+    cascade_count: u32,
+    enabled: u32,
+    _padding: [u32; 2],
+}
+
+/// Cascaded shadow maps for the terrain: a depth pass per cascade from the
+/// light's point of view, sampled with PCF in the terrain fragment shader
+pub struct ShadowMaps {
+    settings: ShadowSettings,
+    pipeline: RenderPipeline,
+
+    // Depth array with one layer per cascade, rebuilt when the resolution changes
+    _texture: Texture,
+    cascade_views: Vec<TextureView>,
+    sampler: Sampler,
+
+    // Light matrix per cascade, at cascade_stride intervals, for the depth passes
+    cascade_buffer: Buffer,
+    cascade_bind_groups: Vec<BindGroup>,
+    cascade_stride: u64,
+
+    // Group 3 of the terrain render pipeline
+    params_buffer: Buffer,
+    sample_layout: BindGroupLayout,
+    sample_bind_group: BindGroup,
+}
+
+impl ShadowMaps {
+    /// Build the depth pipeline from `vs_shadow` in the terrain shader, which
+    /// reads chunks through the terrain's camera and chunk bind group layouts
+    pub fn new(
+        device: &Device,
+        shader: &ShaderModule,
+        vertex_layout: VertexBufferLayout<'_>,
+        camera_layout: &BindGroupLayout,
+        chunk_layout: &BindGroupLayout,
+        compilation_options: &PipelineCompilationOptions<'_>,
+        settings: ShadowSettings,
+    ) -> Result<Self, String> {
+        settings.validate()?;
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, chunk_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Shadow Depth Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: shader,
+                entry_point: Some("vs_shadow"),
+                buffers: &[vertex_layout],
+                compilation_options: compilation_options.clone(),
+            },
+            fragment: None,
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                cull_mode: None, // Skirts and thin ridges cast from either side
+                ..Default::default()
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let cascade_stride = (std::mem::size_of::<CameraUniform>() as u64)
+            .next_multiple_of(device.limits().min_uniform_buffer_offset_alignment as u64);
+        let cascade_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Shadow Cascade Buffer"),
+            size: cascade_stride * MAX_CASCADES as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let cascade_bind_groups = (0..MAX_CASCADES as u64)
+            .map(|i| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some(&format!("Shadow Cascade Bind Group {}", i)),
+                    layout: camera_layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: &cascade_buffer,
+                            offset: i * cascade_stride,
+                            size: BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
+                        }),
+                    }],
+                })
+            })
+            .collect();
+
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Shadow Params Buffer"),
+            size: std::mem::size_of::<ShadowParams>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            compare: Some(CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let sample_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Shadow Sample Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Depth,
+                        view_dimension: TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Comparison),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let (texture, cascade_views, sample_bind_group) =
+            Self::create_maps(device, &sample_layout, &sampler, &params_buffer, settings.resolution);
+
+        Ok(Self {
+            settings,
+            pipeline,
+            _texture: texture,
+            cascade_views,
+            sampler,
+            cascade_buffer,
+            cascade_bind_groups,
+            cascade_stride,
+            params_buffer,
+            sample_layout,
+            sample_bind_group,
+        })
+    }
+
+    /// Depth array texture, a render view per cascade, and the sampling bind group
+    fn create_maps(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        params_buffer: &Buffer,
+        resolution: u32,
+    ) -> (Texture, Vec<TextureView>, BindGroup) {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Shadow Map Texture"),
+            size: Extent3d {
+                width: resolution,
+                height: resolution,
+                depth_or_array_layers: MAX_CASCADES as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let cascade_views = (0..MAX_CASCADES as u32)
+            .map(|layer| {
+                texture.create_view(&TextureViewDescriptor {
+                    label: Some("Shadow Cascade View"),
+                    dimension: Some(TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let array_view = texture.create_view(&TextureViewDescriptor {
+            label: Some("Shadow Map View"),
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Shadow Sample Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&array_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        (texture, cascade_views, bind_group)
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    /// Apply new settings, rebuilding the shadow maps if the resolution changed
+    pub fn update_settings(&mut self, device: &Device, settings: ShadowSettings) -> Result<(), String> {
+        settings.validate()?;
+        if settings.resolution != self.settings.resolution {
+            let (texture, cascade_views, bind_group) = Self::create_maps(
+                device,
+                &self.sample_layout,
+                &self.sampler,
+                &self.params_buffer,
+                settings.resolution,
+            );
+            self._texture = texture;
+            self.cascade_views = cascade_views;
+            self.sample_bind_group = bind_group;
+        }
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Layout of the group the terrain fragment shader samples shadows from
+    pub(crate) fn sample_layout(&self) -> &BindGroupLayout {
+        &self.sample_layout
+    }
+
+    pub(crate) fn sample_bind_group(&self) -> &BindGroup {
+        &self.sample_bind_group
+    }
+
+    /// Fit the cascades to the camera, write the sampling uniforms and record a
+    /// depth pass per cascade. `draw_casters` sets the chunk geometry and draws
+    /// the chunks that can cast into a cascade. Does nothing but tell the
+    /// fragment shader to skip shadows when disabled.
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        queue: &Queue,
+        camera: &dyn CameraController,
+        light_dir: Vec3,
+        mut draw_casters: impl FnMut(&mut RenderPass<'_>, &ShadowCascade),
+    ) {
+        let mut params = ShadowParams::zeroed();
+        if !self.settings.enabled {
+            queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+            return;
+        }
+
+        let cascades = fit_cascades(camera, light_dir, &self.settings);
+        let forward = camera.view_matrix().inverse().transform_vector3(Vec3::NEG_Z).normalize();
+        for (i, cascade) in cascades.iter().enumerate() {
+            params.light_view_proj[i] = cascade.view_proj.to_cols_array_2d();
+            params.split_depths[i] = cascade.split_depth;
+            params.texel_sizes[i] = cascade.texel_size;
+        }
+        params.camera_forward = forward.extend(0.0).to_array();
+        params.cascade_count = cascades.len() as u32;
+        params.enabled = 1;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+
+        for (i, cascade) in cascades.iter().enumerate() {
+            let uniform = CameraUniform {
+                view_proj: cascade.view_proj.to_cols_array_2d(),
+                camera_pos: camera.position().to_array(),
+                _padding: 0.0,
+            };
+            queue.write_buffer(
+                &self.cascade_buffer,
+                i as u64 * self.cascade_stride,
+                bytemuck::cast_slice(&[uniform]),
+            );
+
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Shadow Depth Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &self.cascade_views[i],
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.cascade_bind_groups[i], &[]);
+            draw_casters(&mut render_pass, cascade);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::FlyCamera;
+
+    #[test]
+    fn cascades_cover_their_slice_of_the_view() {
+        let mut camera = FlyCamera::new(16.0 / 9.0);
+        camera.position = Vec3::new(100.0, 300.0, -50.0);
+        camera.yaw = 0.7;
+        camera.pitch = -0.3;
+        let light = Vec3::new(0.4, 0.7, 0.5);
+        let settings = ShadowSettings::default();
+        let cascades = fit_cascades(&camera, light, &settings);
+        assert_eq!(cascades.len(), DEFAULT_CASCADE_COUNT as usize);
+        assert!((cascades.last().unwrap().split_depth - DEFAULT_SHADOW_DISTANCE).abs() < 1.0);
+
+        // Points on the view axis within each slice land inside that cascade's map
+        let forward = camera.view_matrix().inverse().transform_vector3(Vec3::NEG_Z);
+        for pair in cascades.windows(2) {
+            assert!(pair[0].split_depth < pair[1].split_depth);
+            assert!(pair[0].texel_size < pair[1].texel_size);
+        }
+        for cascade in &cascades {
+            for t in [0.01, 0.5, 0.99] {
+                let depth = cascade.near_depth + (cascade.split_depth - cascade.near_depth) * t;
+                let p = cascade.view_proj.project_point3(camera.position + forward * depth);
+                assert!(p.x.abs() <= 1.0 && p.y.abs() <= 1.0, "{:?}", p);
+                assert!((0.0..=1.0).contains(&p.z), "{:?}", p);
+            }
+        }
+    }
+}
@@ -119,6 +119,34 @@ impl Default for SkySettings {
     }
 }
 
+/// Directional light from a sun in the sky
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SunLight {
+    pub direction: Vec3, // Normalized, towards the light
+    pub color: [f32; 3],
+}
+
+/// Rotation and visibility of the sky objects over a day/night cycle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkyCycle {
+    pub sun_angle: f32,   // Rotation of the suns about the world Z axis (radians)
+    pub night_angle: f32, // Rotation of the moons and stars
+    pub star_visibility: f32,
+    pub moon_visibility: f32,
+}
+
+impl Default for SkyCycle {
+    /// Objects at their generated positions, fully visible
+    fn default() -> Self {
+        Self {
+            sun_angle: 0.0,
+            night_angle: 0.0,
+            star_visibility: 1.0,
+            moon_visibility: 1.0,
+        }
+    }
+}
+
 /// A single sky object (star, sun, or moon)
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -139,6 +167,10 @@ struct SkyUniforms {
     view_proj: [[f32; 4]; 4],
     camera_pos: [f32; 3],
     time: f32,
+    sun_angle: f32,
+    night_angle: f32,
+    star_visibility: f32,
+    moon_visibility: f32,
 }
 
 #[derive(Default, Copy, Clone)]
@@ -159,6 +191,9 @@ pub struct SkyRenderer {
     object_buffer: Buffer,
     object_count: u32,
     object_cache: Vec<SkyObject>,
+    primary_sun: Option<SunLight>, // Highest sun, which lights the terrain
+    primary_moon: Option<SunLight>, // Highest moon, which lights the terrain at night
+    cycle: SkyCycle,
 
     // Uniforms
     uniform_buffer: Buffer,
@@ -300,6 +335,9 @@ impl SkyRenderer {
             object_buffer,
             object_count: 0,
             object_cache: Vec::new(),
+            primary_sun: None,
+            primary_moon: None,
+            cycle: SkyCycle::default(),
             uniform_buffer,
             bind_group,
             render_pipeline,
@@ -362,6 +400,8 @@ impl SkyRenderer {
         let generation = self.generate_all_objects();
         self.object_cache = generation.objects;
         self.object_count = self.object_cache.len() as u32;
+        self.primary_sun = self.highest_object(1);
+        self.primary_moon = self.highest_object(2);
         self.needs_regeneration = false;
         self.objects_dirty = true;
         log::info!(
@@ -373,6 +413,33 @@ impl SkyRenderer {
         );
     }
 
+    /// Highest object of a type, as a light from its direction and color
+    fn highest_object(&self, object_type: u32) -> Option<SunLight> {
+        self.object_cache
+            .iter()
+            .filter(|obj| obj.object_type == object_type)
+            .max_by(|a, b| a.position[1].total_cmp(&b.position[1]))
+            .map(|obj| SunLight {
+                direction: Vec3::from(obj.position).normalize(),
+                color: obj.color,
+            })
+    }
+
+    /// Highest sun in the sky, if there are any
+    pub fn primary_sun(&self) -> Option<SunLight> {
+        self.primary_sun
+    }
+
+    /// Highest moon in the sky, if there are any
+    pub fn primary_moon(&self) -> Option<SunLight> {
+        self.primary_moon
+    }
+
+    /// Rotate and fade the sky objects for the time of day
+    pub fn set_cycle(&mut self, cycle: SkyCycle) {
+        self.cycle = cycle;
+    }
+
     /// Update settings and mark for regeneration if needed
     pub fn update_settings(&mut self, settings: SkySettings) {
         if self.settings != settings {
@@ -460,6 +527,10 @@ impl SkyRenderer {
             view_proj: camera_view_proj,
             camera_pos: [camera_pos.x, camera_pos.y, camera_pos.z],
             time: self.current_time,
+            sun_angle: self.cycle.sun_angle,
+            night_angle: self.cycle.night_angle,
+            star_visibility: self.cycle.star_visibility,
+            moon_visibility: self.cycle.moon_visibility,
         };
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
     }
@@ -1,26 +1,57 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{Vec3, Vec4};
+use glam::{Vec2, Vec3, Vec4};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver};
 use wgpu::util::DeviceExt;
 use wgpu::*;
 
-use crate::camera::FlyCamera;
+use crate::camera::{frustum_planes, CameraController};
+use crate::culling::{ChunkCuller, ChunkInfo, LodRange};
+use crate::daycycle::Atmosphere;
+use crate::erosion::{generation_border, warn_if_iterations_clamped, ErosionPipeline};
+use crate::shadows::{ShadowCascade, ShadowMaps, ShadowSettings};
+use crate::sky::SunLight;
+use crate::water::WaterRenderer;
 
 // Constants matching shader
-const CHUNK_SIZE: u32 = 64;
-const CHUNK_WORLD_SIZE: f32 = 256.0;
-const VIEW_RADIUS: i32 = 16; // 33x33 chunks visible
-const MAX_CHUNKS: usize = 1089; // 33x33 = 1089
+pub(crate) const HEIGHT_APRON: u32 = 1; // Extra samples stored on each side of a chunk, for edge normals
 const TERRAIN_WORKGROUP_SIZE: u32 = 8; // Must match @workgroup_size in shader
+pub const DEFAULT_CHUNK_BUDGET: usize = 24; // Chunks generated per frame while streaming
+const HEIGHT_READBACK_CHUNKS: usize = 16; // Chunk heights copied back to the CPU per readback
+
+// TerrainQuality limits
+const MIN_CHUNK_SIZE: u32 = 8;
+const MAX_CHUNK_SIZE: u32 = 256; // Keeps chunk vertex indices within u16 for mesh export
+const MAX_VIEW_RADIUS: i32 = 48;
+const MAX_HEIGHT_BUFFER_BYTES: u64 = 128 * 1024 * 1024; // Shared height buffer, within the default storage binding limit
+const HEIGHT_SLOT_ALIGNMENT: u64 = 256; // Default min_storage_buffer_offset_alignment
+pub(crate) const MAX_AO_BORDER: u32 = 48; // Samples; caps the AO radius and the padding it adds
+
+// Level of detail: vertex stride per level for a 64-vertex chunk (scaled for other
+// sizes) and the camera distances, in chunks, at which chunks switch to the next level
+const LOD_STRIDES: [u32; 4] = [1, 3, 7, 21];
+pub(crate) const LOD_DISTANCES: [f32; 3] = [2.5, 5.5, 11.0];
 
 // Default rendering constants (for TerrainSettings::default())
 // Note: Presets use different values (e.g., ambient 0.35 vs default 0.25)
 pub const DEFAULT_FOG_START: f32 = 800.0;
 pub const DEFAULT_FOG_DISTANCE: f32 = 3000.0;
+pub const DEFAULT_EROSION_RAIN: f32 = 0.2;
+pub const DEFAULT_EROSION_SEDIMENT_CAPACITY: f32 = 1.0;
+pub const DEFAULT_EROSION_EVAPORATION: f32 = 0.05;
+pub const DEFAULT_THERMAL_TALUS_ANGLE: f32 = 35.0;
+pub const DEFAULT_THERMAL_STRENGTH: f32 = 0.5;
+pub const DEFAULT_SUN_DIRECTION: [f32; 3] = [0.4, 0.7, 0.5];
+pub const DEFAULT_AO_STRENGTH: f32 = 0.6;
+pub const DEFAULT_AO_RADIUS: f32 = 64.0;
+pub const DEFAULT_WAVE_STRENGTH: f32 = 0.15;
+pub const DEFAULT_WAVE_SPEED: f32 = 1.0;
+pub const DEFAULT_WATER_CLARITY: f32 = 30.0;
+pub const DEFAULT_FOAM_WIDTH: f32 = 3.0;
 
 /// Terrain generation settings that can be modified at runtime
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct TerrainSettings {
     // Generation parameters
@@ -33,10 +64,37 @@ pub struct TerrainSettings {
     pub pattern_type: u32,
     pub seed: u32,
 
+    // Hydraulic erosion (0 iterations disables it)
+    pub erosion_iterations: u32, // Clamped to MAX_HYDRAULIC_ITERATIONS (32)
+    pub erosion_rain: f32,
+    pub erosion_sediment_capacity: f32,
+    pub erosion_evaporation: f32,
+
+    // Thermal erosion (0 iterations disables it)
+    pub thermal_iterations: u32, // Clamped to MAX_THERMAL_ITERATIONS (32)
+    pub thermal_talus_angle: f32, // Degrees; steeper slopes shed material
+    pub thermal_strength: f32,    // Fraction of the excess moved per iteration (0-1)
+
     // Lighting/fog
     pub ambient: f32,
     pub fog_start: f32,
     pub fog_distance: f32,
+    pub sun_direction: [f32; 3], // Towards the light, when not taken from the sky
+    pub sun_color: [f32; 3],     // Light color (RGB 0-1), when not taken from the sky
+    pub sun_intensity: f32,
+    pub sun_from_sky: bool,      // Light from the highest sun in SkySettings, if there is one
+
+    // Ambient occlusion, baked per chunk from the heightfield
+    pub ao_strength: f32, // 0 disables the darkening, 1 is full strength
+    pub ao_radius: f32,   // World units searched for occluders; 0 skips the bake
+
+    // Water surface; when disabled, terrain below sea level keeps the water color bands
+    pub water_enabled: bool,
+    pub sea_level: f32,       // Height of the water surface; color bands are measured from it
+    pub wave_strength: f32,   // Steepest slope of the wave normals (0 is a flat mirror)
+    pub wave_speed: f32,      // Multiplier on the natural speed of the waves
+    pub water_clarity: f32,   // World units light travels through water before fading to 1/e
+    pub foam_width: f32,      // Water depth, in world units, below which shoreline foam appears
 
     // Terrain colors (RGB 0-1)
     pub color_abyss: [f32; 3],
@@ -64,9 +122,28 @@ impl Default for TerrainSettings {
             roughness: 0.35,
             pattern_type: 4,
             seed: 0,
+            erosion_iterations: 0,
+            erosion_rain: DEFAULT_EROSION_RAIN,
+            erosion_sediment_capacity: DEFAULT_EROSION_SEDIMENT_CAPACITY,
+            erosion_evaporation: DEFAULT_EROSION_EVAPORATION,
+            thermal_iterations: 0,
+            thermal_talus_angle: DEFAULT_THERMAL_TALUS_ANGLE,
+            thermal_strength: DEFAULT_THERMAL_STRENGTH,
             ambient: 0.25, // Note: Presets use PRESET_AMBIENT (0.35) instead
             fog_start: DEFAULT_FOG_START,
             fog_distance: DEFAULT_FOG_DISTANCE,
+            sun_direction: DEFAULT_SUN_DIRECTION,
+            sun_color: [1.0, 1.0, 1.0],
+            sun_intensity: 1.0,
+            sun_from_sky: true,
+            ao_strength: DEFAULT_AO_STRENGTH,
+            ao_radius: DEFAULT_AO_RADIUS,
+            water_enabled: false,
+            sea_level: 0.0,
+            wave_strength: DEFAULT_WAVE_STRENGTH,
+            wave_speed: DEFAULT_WAVE_SPEED,
+            water_clarity: DEFAULT_WATER_CLARITY,
+            foam_width: DEFAULT_FOAM_WIDTH,
             color_abyss: [0.4, 0.4, 0.4],
             color_deep_water: [0.6, 0.6, 0.6],
             color_shallow_water: [0.7, 0.7, 0.7],
@@ -81,6 +158,176 @@ impl Default for TerrainSettings {
     }
 }
 
+impl TerrainSettings {
+    /// Whether two settings produce the same chunk heights. Lighting, fog and
+    /// color fields only affect the color uniform.
+    pub fn same_geometry(&self, other: &Self) -> bool {
+        self.terrain_scale == other.terrain_scale
+            && self.height_scale == other.height_scale
+            && self.octaves == other.octaves
+            && self.warp_strength == other.warp_strength
+            && self.height_variance == other.height_variance
+            && self.roughness == other.roughness
+            && self.pattern_type == other.pattern_type
+            && self.seed == other.seed
+            && self.erosion_iterations == other.erosion_iterations
+            && self.erosion_rain == other.erosion_rain
+            && self.erosion_sediment_capacity == other.erosion_sediment_capacity
+            && self.erosion_evaporation == other.erosion_evaporation
+            && self.thermal_iterations == other.thermal_iterations
+            && self.thermal_talus_angle == other.thermal_talus_angle
+            && self.thermal_strength == other.thermal_strength
+            && self.ao_radius == other.ao_radius
+    }
+
+    /// Unlit color for a world height and slope (0 flat, 1 vertical), matching
+    /// `fs_main` in terrain.wgsl. Bands are measured from sea level.
+    pub fn base_color(&self, height: f32, slope: f32) -> Vec3 {
+        let abyss = Vec3::from(self.color_abyss);
+        let deep_water = Vec3::from(self.color_deep_water);
+        let shallow_water = Vec3::from(self.color_shallow_water);
+        let sand = Vec3::from(self.color_sand);
+        let grass = Vec3::from(self.color_grass);
+        let dark_grass = grass * 0.7;
+        let rock = Vec3::from(self.color_rock);
+        let dark_rock = rock * 0.7;
+        let snow = Vec3::from(self.color_snow);
+
+        let h = height - self.sea_level;
+        let mut color = if self.water_enabled && h < 0.0 {
+            // Seabed; the water surface is drawn separately
+            sand.lerp(dark_rock, (-h / 100.0).clamp(0.0, 1.0))
+        } else if h < -100.0 {
+            abyss
+        } else if h < -5.0 {
+            abyss.lerp(deep_water, (h + 100.0) / 95.0)
+        } else if h < 0.0 {
+            deep_water.lerp(shallow_water, (h + 5.0) / 5.0)
+        } else if h < 5.0 {
+            sand.lerp(grass, h / 5.0)
+        } else if h < 40.0 {
+            grass.lerp(dark_grass, (h - 5.0) / 35.0)
+        } else if h < 70.0 {
+            dark_grass.lerp(rock, (h - 40.0) / 30.0)
+        } else if h < 100.0 {
+            rock.lerp(dark_rock, (h - 70.0) / 30.0)
+        } else {
+            dark_rock.lerp(snow, ((h - 100.0) / 20.0).clamp(0.0, 1.0))
+        };
+
+        if h > 5.0 {
+            let t = ((slope - 0.35) / 0.2).clamp(0.0, 1.0);
+            color = color.lerp(rock, t * t * (3.0 - 2.0 * t));
+        }
+
+        if h > 90.0 && slope < 0.35 {
+            let snow_factor = (h - 90.0) / 30.0 * (1.0 - slope / 0.35);
+            color = color.lerp(snow, snow_factor.clamp(0.0, 1.0));
+        }
+
+        color
+    }
+}
+
+/// Chunk resolution and streaming distance. Changing these rebuilds the chunk pool.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainQuality {
+    pub chunk_size: u32,       // Vertices per chunk edge
+    pub chunk_world_size: f32, // World units per chunk edge
+    pub view_radius: i32,      // Chunks kept loaded in each direction around the camera
+}
+
+impl Default for TerrainQuality {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64,
+            chunk_world_size: 256.0,
+            view_radius: 16, // 33x33 chunks visible
+        }
+    }
+}
+
+impl TerrainQuality {
+    /// Reject values the chunk pool or GPU buffers can't handle
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&self.chunk_size) {
+            return Err(format!(
+                "chunk_size must be between {} and {}, got {}",
+                MIN_CHUNK_SIZE, MAX_CHUNK_SIZE, self.chunk_size
+            ));
+        }
+        if !(self.chunk_world_size.is_finite() && self.chunk_world_size > 0.0) {
+            return Err(format!("chunk_world_size must be positive, got {}", self.chunk_world_size));
+        }
+        if !(1..=MAX_VIEW_RADIUS).contains(&self.view_radius) {
+            return Err(format!(
+                "view_radius must be between 1 and {}, got {}",
+                MAX_VIEW_RADIUS, self.view_radius
+            ));
+        }
+
+        let bytes = self.max_chunks() as u64 * self.height_slot_bytes(HEIGHT_SLOT_ALIGNMENT);
+        if bytes > MAX_HEIGHT_BUFFER_BYTES {
+            return Err(format!(
+                "chunk_size {} with view_radius {} needs {} MiB of height storage (limit {} MiB)",
+                self.chunk_size,
+                self.view_radius,
+                bytes / (1024 * 1024),
+                MAX_HEIGHT_BUFFER_BYTES / (1024 * 1024)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Chunk slots needed to cover the view radius
+    pub fn max_chunks(&self) -> usize {
+        let span = (self.view_radius * 2 + 1) as usize;
+        span * span
+    }
+
+    /// Distance between neighbouring height samples
+    pub fn sample_spacing(&self) -> f32 {
+        self.chunk_world_size / (self.chunk_size - 1) as f32
+    }
+
+    /// Samples per side of a chunk's height buffer, including the apron
+    pub fn height_grid_size(&self) -> u32 {
+        self.chunk_size + 2 * HEIGHT_APRON
+    }
+
+    /// Bytes reserved per chunk slot in the shared height buffer, so that
+    /// each slot starts on a storage binding offset boundary
+    pub(crate) fn height_slot_bytes(&self, alignment: u64) -> u64 {
+        let grid = self.height_grid_size() as u64;
+        (grid * grid * 4).next_multiple_of(alignment)
+    }
+
+    /// Vertex stride for each level of detail. Every stride divides `chunk_size - 1`,
+    /// so sizes with few divisors get fewer levels.
+    pub(crate) fn lod_strides(&self) -> Vec<u32> {
+        let segments = self.chunk_size - 1;
+        let scale = segments as f32 / 63.0;
+        let mut strides: Vec<u32> = Vec::with_capacity(LOD_STRIDES.len());
+        for &target in &LOD_STRIDES {
+            let limit = (target as f32 * scale).max(1.0) as u32;
+            let stride = (1..=limit).rev().find(|d| segments.is_multiple_of(*d)).unwrap_or(1);
+            if strides.last() != Some(&stride) {
+                strides.push(stride);
+            }
+        }
+        strides
+    }
+
+    /// Values for the pipeline-overridable constants in the terrain and erosion shaders
+    pub(crate) fn shader_constants(&self) -> HashMap<String, f64> {
+        HashMap::from([
+            ("CHUNK_SIZE".to_string(), self.chunk_size as f64),
+            ("CHUNK_WORLD_SIZE".to_string(), self.chunk_world_size as f64),
+        ])
+    }
+}
+
 /// Chunk coordinate in chunk-space
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub struct ChunkCoord {
@@ -93,33 +340,53 @@ impl ChunkCoord {
         Self { x, z }
     }
 
-    pub fn from_world_pos(pos: Vec3) -> Self {
+    pub fn from_world_pos(pos: Vec3, quality: &TerrainQuality) -> Self {
         Self {
-            x: (pos.x / CHUNK_WORLD_SIZE).floor() as i32,
-            z: (pos.z / CHUNK_WORLD_SIZE).floor() as i32,
+            x: (pos.x / quality.chunk_world_size).floor() as i32,
+            z: (pos.z / quality.chunk_world_size).floor() as i32,
         }
     }
 
-    pub fn world_offset(&self) -> [f32; 2] {
+    /// Global index of the chunk's first height sample (chunks share edge samples)
+    pub fn sample_origin(&self, quality: &TerrainQuality) -> [i32; 2] {
+        let edge = (quality.chunk_size - 1) as i32;
+        [self.x * edge, self.z * edge]
+    }
+
+    pub fn world_offset(&self, quality: &TerrainQuality) -> [f32; 2] {
         [
-            self.x as f32 * CHUNK_WORLD_SIZE,
-            self.z as f32 * CHUNK_WORLD_SIZE,
+            self.x as f32 * quality.chunk_world_size,
+            self.z as f32 * quality.chunk_world_size,
         ]
     }
 
+    /// Level of detail for this chunk, from the camera's horizontal distance to its centre.
+    /// LOD_DISTANCES are spaced wider than a chunk, so neighbours differ by at most one level.
+    pub fn lod_for(&self, camera_pos: Vec3, quality: &TerrainQuality) -> usize {
+        let offset = self.world_offset(quality);
+        let center = Vec2::new(offset[0], offset[1]) + Vec2::splat(quality.chunk_world_size * 0.5);
+        let distance = center.distance(Vec2::new(camera_pos.x, camera_pos.z)) / quality.chunk_world_size;
+        LOD_DISTANCES.iter().filter(|&&d| distance > d).count()
+    }
+
     /// Test if this chunk's AABB is visible within the frustum planes
-    /// Uses a conservative test - returns true if chunk might be visible
-    pub fn is_visible_in_frustum(&self, frustum_planes: &[Vec4; 6], height_scale: f32) -> bool {
-        let offset = self.world_offset();
+    /// Uses a conservative test - returns true if chunk might be visible.
+    /// CPU reference for `cull_chunks` in culling.wgsl.
+    pub fn is_visible_in_frustum(
+        &self,
+        frustum_planes: &[Vec4; 6],
+        bounds: HeightBounds,
+        quality: &TerrainQuality,
+    ) -> bool {
+        let offset = self.world_offset(quality);
 
         // Chunk AABB bounds
         let min_x = offset[0];
-        let max_x = offset[0] + CHUNK_WORLD_SIZE;
+        let max_x = offset[0] + quality.chunk_world_size;
         let min_z = offset[1];
-        let max_z = offset[1] + CHUNK_WORLD_SIZE;
-        // Use conservative height bounds (terrain can go from -height_scale to +height_scale)
-        let min_y = -height_scale * 0.5;
-        let max_y = height_scale;
+        let max_z = offset[1] + quality.chunk_world_size;
+        let min_y = bounds.min;
+        let max_y = bounds.max;
 
         // Test AABB against each frustum plane
         for plane in frustum_planes {
@@ -139,6 +406,13 @@ impl ChunkCoord {
     }
 }
 
+/// Lowest and highest terrain height within a chunk
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeightBounds {
+    pub min: f32,
+    pub max: f32,
+}
+
 /// State of a chunk slot
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ChunkState {
@@ -151,18 +425,49 @@ pub struct ChunkSlot {
     pub state: ChunkState,
     pub coord: Option<ChunkCoord>,
     pub params_buffer: Buffer,
-    pub _height_buffer: Buffer,
-    pub uniform_buffer: Buffer,
     pub compute_bind_group: BindGroup,
-    pub render_bind_group: BindGroup,
+    pub bounds_bind_group: BindGroup,
+    /// Made when the chunk is first generated through the erosion scratch grid
+    pub scratch_bind_groups: Option<ScratchBindGroups>,
+    /// Read back from the GPU a few frames after generation; None until then
+    pub height_bounds: Option<HeightBounds>,
+    /// Generated heights (erosion included), `height_grid_size` per row with the apron,
+    /// read back like the bounds
+    pub heights: Option<Vec<f32>>,
+    pub generation: u64, // Distinguishes successive chunks generated into this slot
+    pub settings_version: u64, // Stale once the renderer's version moves past it
     pub last_used_frame: u64,
 }
 
+/// A chunk slot's bind groups over the shared erosion scratch grid
+pub struct ScratchBindGroups {
+    pub compute: BindGroup, // compute_height into the padded erosion grid
+    pub erosion: BindGroup,
+    pub ao: BindGroup, // Bakes occlusion from the padded erosion grid
+}
+
+/// Bind group layouts shared by every chunk slot
+struct ChunkSlotLayouts<'a> {
+    compute: &'a BindGroupLayout,
+    bounds: &'a BindGroupLayout,
+}
+
+/// AO search radius in samples, capped to MAX_AO_BORDER
+fn ao_radius_samples(settings: &TerrainSettings, quality: &TerrainQuality) -> f32 {
+    (settings.ao_radius.max(0.0) / quality.sample_spacing()).min(MAX_AO_BORDER as f32)
+}
+
+/// Extra samples needed on each side of a chunk so the AO rays near its edges
+/// see the same terrain as its neighbours. Zero when AO is disabled.
+pub(crate) fn ao_border(settings: &TerrainSettings, quality: &TerrainQuality) -> u32 {
+    ao_radius_samples(settings, quality).ceil() as u32
+}
+
 /// Compute shader parameters - must match shader layout
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
-struct ComputeParams {
-    chunk_offset: [f32; 2],
+pub(crate) struct ComputeParams {
+    chunk_origin: [i32; 2],
     terrain_scale: f32,
     height_scale: f32,
     octaves: u32,
@@ -171,7 +476,45 @@ struct ComputeParams {
     roughness: f32,
     pattern_type: u32,
     seed: u32,
-    _padding: [f32; 2], // Align to 16 bytes
+    border: u32,
+    erosion_rain: f32,
+    erosion_sediment_capacity: f32,
+    erosion_evaporation: f32,
+    thermal_talus: f32, // Tangent of the talus angle
+    thermal_strength: f32,
+    slot: u32,
+    ao_radius: f32, // Samples; 0 skips the AO bake
+}
+
+impl ComputeParams {
+    /// Parameters for generating a chunk plus `border` extra samples on each side
+    pub(crate) fn new(settings: &TerrainSettings, quality: &TerrainQuality, coord: ChunkCoord, border: u32) -> Self {
+        Self {
+            chunk_origin: coord.sample_origin(quality),
+            terrain_scale: settings.terrain_scale,
+            height_scale: settings.height_scale,
+            octaves: settings.octaves,
+            warp_strength: settings.warp_strength,
+            height_variance: settings.height_variance,
+            roughness: settings.roughness,
+            pattern_type: settings.pattern_type,
+            seed: settings.seed,
+            border,
+            erosion_rain: settings.erosion_rain,
+            erosion_sediment_capacity: settings.erosion_sediment_capacity,
+            erosion_evaporation: settings.erosion_evaporation,
+            thermal_talus: settings.thermal_talus_angle.to_radians().tan(),
+            thermal_strength: settings.thermal_strength,
+            slot: 0,
+            ao_radius: ao_radius_samples(settings, quality),
+        }
+    }
+
+    /// Target chunk slot for the height bounds written by `reduce_height_bounds`
+    pub(crate) fn with_slot(mut self, slot: usize) -> Self {
+        self.slot = slot as u32;
+        self
+    }
 }
 
 /// Fragment shader color parameters - must match shader layout
@@ -188,10 +531,50 @@ struct ColorParams {
     color_sky: [f32; 4],
     color_sky_top: [f32; 4],
     color_sky_horizon: [f32; 4],
+    sun_direction: [f32; 4],       // Normalized, w unused
+    sun_color: [f32; 4],           // Color times intensity, w unused
     ambient: f32,
     fog_start: f32,
     fog_distance: f32,
-    _padding: f32,
+    ao_strength: f32,
+    sea_level: f32,
+    water_enabled: u32,
+    _padding: [f32; 2],
+}
+
+/// Triangle list indices for a chunk_size x chunk_size vertex grid (CCW seen from above),
+/// using every `stride`-th vertex in each direction
+pub(crate) fn grid_indices(chunk_size: u32, stride: u32) -> Vec<u32> {
+    let mut indices: Vec<u32> = Vec::new();
+    for z in (0..chunk_size - 1).step_by(stride as usize) {
+        for x in (0..chunk_size - 1).step_by(stride as usize) {
+            let tl = z * chunk_size + x;
+            let tr = tl + stride;
+            let bl = tl + stride * chunk_size;
+            let br = bl + stride;
+
+            // Two triangles per quad
+            indices.push(tl);
+            indices.push(bl);
+            indices.push(tr);
+            indices.push(tr);
+            indices.push(bl);
+            indices.push(br);
+        }
+    }
+    indices
+}
+
+/// Grid positions around the chunk edge at the given stride, one list per side
+fn edge_vertices(chunk_size: u32, stride: u32) -> [Vec<(u32, u32)>; 4] {
+    let last = chunk_size - 1;
+    let steps: Vec<u32> = (0..=last).step_by(stride as usize).collect();
+    [
+        steps.iter().map(|&i| (i, 0)).collect(),
+        steps.iter().map(|&i| (last, i)).collect(),
+        steps.iter().map(|&i| (i, last)).collect(),
+        steps.iter().map(|&i| (0, i)).collect(),
+    ]
 }
 
 /// Convert RGB color to RGBA with alpha=1.0 for shader uniform alignment
@@ -199,23 +582,24 @@ fn rgb_to_rgba(rgb: [f32; 3]) -> [f32; 4] {
     [rgb[0], rgb[1], rgb[2], 1.0]
 }
 
-/// Per-chunk uniforms for rendering
-#[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
-struct ChunkUniform {
-    chunk_offset: [f32; 2],
-    _padding: [f32; 2],
-}
-
 /// Vertex data for terrain grid
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 struct TerrainVertex {
     local_uv: [f32; 2],
+    skirt_depth: f32, // How far below the surface this vertex sits (0 except on skirts)
 }
 
 impl TerrainVertex {
-    const ATTRIBS: [VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x2];
+    const ATTRIBS: [VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32];
+
+    fn grid(chunk_size: u32, x: u32, z: u32, skirt_depth: f32) -> Self {
+        let scale = (chunk_size - 1) as f32;
+        Self {
+            local_uv: [x as f32 / scale, z as f32 / scale],
+            skirt_depth,
+        }
+    }
 
     fn desc() -> VertexBufferLayout<'static> {
         VertexBufferLayout {
@@ -226,24 +610,90 @@ impl TerrainVertex {
     }
 }
 
+/// Delivers the result of a `map_async` call
+type MapResult = Receiver<Result<(), BufferAsyncError>>;
+
+/// Staging copy of the chunk info buffer, for reading height bounds back to the CPU
+struct BoundsReadback {
+    staging: Buffer,
+    // Slot generations at the time of the copy, and the map_async result
+    pending: Option<(Vec<u64>, MapResult)>,
+}
+
+impl BoundsReadback {
+    fn new(device: &Device, size: u64) -> Self {
+        let staging = device.create_buffer(&BufferDescriptor {
+            label: Some("Height Bounds Readback Buffer"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self { staging, pending: None }
+    }
+}
+
+/// Staging copies of chunk height slots, for CPU height queries that match the rendered terrain
+struct HeightReadback {
+    staging: Buffer,
+    // Slots and their generations at the time of the copy, and the map_async result
+    pending: Option<(Vec<(usize, u64)>, MapResult)>,
+}
+
+impl HeightReadback {
+    fn new(device: &Device, height_slot_bytes: u64) -> Self {
+        let staging = device.create_buffer(&BufferDescriptor {
+            label: Some("Height Readback Buffer"),
+            size: HEIGHT_READBACK_CHUNKS as u64 * height_slot_bytes,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self { staging, pending: None }
+    }
+}
+
 /// Manages terrain chunks, streaming, and rendering
 pub struct TerrainRenderer {
-    // Shared geometry
+    // Shared geometry; every level of detail lives in one index buffer
     vertex_buffer: Buffer,
     index_buffer: Buffer,
-    index_count: u32,
+    lods: Vec<LodRange>,
 
-    // Chunk pool
+    // Chunk pool. All slots share one height buffer, at height_slot_bytes intervals.
+    height_buffer: Buffer,
+    height_slot_bytes: u64,
+    ao_buffer: Buffer, // Baked occlusion per slot, at ao_slot_bytes intervals
+    ao_slot_bytes: u64,
     slots: Vec<ChunkSlot>,
     coord_to_slot: HashMap<ChunkCoord, usize>,
     current_frame: u64,
 
     // Pipelines
     compute_pipeline: ComputePipeline,
+    bounds_pipeline: ComputePipeline,
+    ao_pipeline: ComputePipeline,
     render_pipeline: RenderPipeline,
 
-    // Bind group layout for compute shader
-    _compute_bind_group_layout: BindGroupLayout,
+    // Bind group layouts for the per-slot scratch bind groups
+    compute_bind_group_layout: BindGroupLayout,
+    ao_bind_group_layout: BindGroupLayout,
+
+    // Post-generation erosion passes
+    erosion: ErosionPipeline,
+
+    // Frustum culling and indirect draw arguments
+    culler: ChunkCuller,
+    chunk_bind_groups: Vec<BindGroup>, // One for multi-draw, otherwise one per level
+
+    // Cascaded shadow maps from the sun
+    shadows: ShadowMaps,
+
+    // Water surface pass at sea level
+    water: WaterRenderer,
+
+    // CPU copies of the per-chunk height bounds and heights
+    bounds_readback: BoundsReadback,
+    height_readback: HeightReadback,
+    chunks_generated: u64,
 
     // Camera uniform buffer
     camera_uniform_buffer: Buffer,
@@ -252,19 +702,49 @@ pub struct TerrainRenderer {
     // Color uniform buffer
     color_uniform_buffer: Buffer,
     color_bind_group: BindGroup,
+    colors_dirty: bool, // Color uniform is rewritten on the next update
+    sky_sun: Option<SunLight>, // Highest sun in the sky, used when `sun_from_sky` is set
+    atmosphere: Option<Atmosphere>, // Day/night cycle override of the sky, ambient and fog settings
 
     // Terrain settings
     pub settings: TerrainSettings,
+    quality: TerrainQuality,
+    surface_format: TextureFormat,
     needs_regeneration: bool,
+
+    // Streaming: chunks generated with an older settings version are redone in place
+    settings_version: u64,
+    chunk_budget: usize,
 }
 
 impl TerrainRenderer {
+    /// The chunk pool starts empty; `update` streams chunks in around the camera
+    /// within the chunk budget, like after a quality change.
     pub fn new(
         device: &Device,
-        queue: &Queue,
         surface_format: TextureFormat,
         settings: TerrainSettings,
+        quality: TerrainQuality,
     ) -> Result<Self, String> {
+        warn_if_iterations_clamped(&settings);
+        Self::build(device, surface_format, settings, quality, ShadowSettings::default())
+    }
+
+    /// Create pipelines, shared geometry and the chunk pool for a quality level
+    fn build(
+        device: &Device,
+        surface_format: TextureFormat,
+        settings: TerrainSettings,
+        quality: TerrainQuality,
+        shadow_settings: ShadowSettings,
+    ) -> Result<Self, String> {
+        quality.validate()?;
+        let shader_constants = quality.shader_constants();
+        let compilation_options = PipelineCompilationOptions {
+            constants: &shader_constants,
+            ..Default::default()
+        };
+
         // Load shader
         let shader_source = include_str!("../shaders/terrain.wgsl");
         let shader = device.create_shader_module(ShaderModuleDescriptor {
@@ -300,28 +780,52 @@ impl TerrainRenderer {
                 ],
             });
 
-        let camera_bind_group_layout =
+        // Height bounds reduction: the compute layout plus the chunk info it writes into
+        let bounds_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: Some("Camera Bind Group Layout"),
-                entries: &[BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                label: Some("Height Bounds Bind Group Layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
             });
 
-        let chunk_bind_group_layout =
+        // AO bake: the compute layout plus the chunk's range of the AO buffer
+        let ao_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: Some("Chunk Bind Group Layout"),
+                label: Some("AO Bind Group Layout"),
                 entries: &[
                     BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: ShaderStages::VERTEX,
+                        visibility: ShaderStages::COMPUTE,
                         ty: BindingType::Buffer {
                             ty: BufferBindingType::Uniform,
                             has_dynamic_offset: false,
@@ -331,9 +835,19 @@ impl TerrainRenderer {
                     },
                     BindGroupLayoutEntry {
                         binding: 1,
-                        visibility: ShaderStages::VERTEX,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::COMPUTE,
                         ty: BindingType::Buffer {
-                            ty: BufferBindingType::Storage { read_only: true },
+                            ty: BufferBindingType::Storage { read_only: false },
                             has_dynamic_offset: false,
                             min_binding_size: None,
                         },
@@ -342,6 +856,42 @@ impl TerrainRenderer {
                 ],
             });
 
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Camera Bind Group Layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let vertex_storage_entry = |binding: u32| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::VERTEX,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let chunk_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Chunk Bind Group Layout"),
+                entries: &[
+                    vertex_storage_entry(0), // Chunk info per slot
+                    vertex_storage_entry(1), // Shared heights
+                    vertex_storage_entry(2), // Visible slots
+                    vertex_storage_entry(3), // Shared baked AO
+                ],
+            });
+
         let color_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("Color Bind Group Layout"),
@@ -357,6 +907,16 @@ impl TerrainRenderer {
                 }],
             });
 
+        let shadows = ShadowMaps::new(
+            device,
+            &shader,
+            TerrainVertex::desc(),
+            &camera_bind_group_layout,
+            &chunk_bind_group_layout,
+            &compilation_options,
+            shadow_settings,
+        )?;
+
         // Create compute pipeline
         let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Compute Pipeline Layout"),
@@ -369,14 +929,49 @@ impl TerrainRenderer {
             layout: Some(&compute_pipeline_layout),
             module: &shader,
             entry_point: Some("compute_height"),
-            compilation_options: Default::default(),
+            compilation_options: compilation_options.clone(),
+            cache: None,
+        });
+
+        let bounds_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Height Bounds Pipeline Layout"),
+            bind_group_layouts: &[&bounds_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let bounds_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Height Bounds Pipeline"),
+            layout: Some(&bounds_pipeline_layout),
+            module: &shader,
+            entry_point: Some("reduce_height_bounds"),
+            compilation_options: compilation_options.clone(),
+            cache: None,
+        });
+
+        let ao_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("AO Pipeline Layout"),
+            bind_group_layouts: &[&ao_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let ao_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("AO Bake Pipeline"),
+            layout: Some(&ao_pipeline_layout),
+            module: &shader,
+            entry_point: Some("bake_ambient_occlusion"),
+            compilation_options: compilation_options.clone(),
             cache: None,
         });
 
         // Create render pipeline
         let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&camera_bind_group_layout, &chunk_bind_group_layout, &color_bind_group_layout],
+            bind_group_layouts: &[
+                &camera_bind_group_layout,
+                &chunk_bind_group_layout,
+                &color_bind_group_layout,
+                shadows.sample_layout(),
+            ],
             push_constant_ranges: &[],
         });
 
@@ -387,7 +982,7 @@ impl TerrainRenderer {
                 module: &shader,
                 entry_point: Some("vs_main"),
                 buffers: &[TerrainVertex::desc()],
-                compilation_options: Default::default(),
+                compilation_options: compilation_options.clone(),
             },
             fragment: Some(FragmentState {
                 module: &shader,
@@ -397,7 +992,7 @@ impl TerrainRenderer {
                     blend: Some(BlendState::REPLACE),
                     write_mask: ColorWrites::ALL,
                 })],
-                compilation_options: Default::default(),
+                compilation_options,
             }),
             primitive: PrimitiveState {
                 topology: PrimitiveTopology::TriangleList,
@@ -421,7 +1016,7 @@ impl TerrainRenderer {
         });
 
         // Create shared geometry
-        let (vertex_buffer, index_buffer, index_count) = Self::create_grid_buffers(device);
+        let (vertex_buffer, index_buffer, lods) = Self::create_grid_buffers(device, &quality);
 
         // Create camera uniform buffer
         let camera_uniform_buffer = device.create_buffer(&BufferDescriptor {
@@ -457,94 +1052,188 @@ impl TerrainRenderer {
             }],
         });
 
+        let water = WaterRenderer::new(device, surface_format, &camera_bind_group_layout, &color_bind_group_layout);
+
+        let erosion = ErosionPipeline::new(device, &quality);
+
         // Pre-allocate chunk slots
-        let mut slots = Vec::with_capacity(MAX_CHUNKS);
-        for i in 0..MAX_CHUNKS {
-            slots.push(Self::create_chunk_slot(
-                device,
-                &chunk_bind_group_layout,
-                &compute_bind_group_layout,
-                i,
-            ));
+        let max_chunks = quality.max_chunks();
+        let height_slot_bytes =
+            quality.height_slot_bytes(device.limits().min_storage_buffer_offset_alignment as u64);
+        let height_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Chunk Height Buffer"),
+            size: max_chunks as u64 * height_slot_bytes,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let ao_bytes = (quality.chunk_size * quality.chunk_size * 4) as u64;
+        let ao_slot_bytes =
+            ao_bytes.next_multiple_of(device.limits().min_storage_buffer_offset_alignment as u64);
+        let ao_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Chunk AO Buffer"),
+            size: max_chunks as u64 * ao_slot_bytes,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let culler = ChunkCuller::new(device, &quality, &lods);
+        let slot_layouts = ChunkSlotLayouts {
+            compute: &compute_bind_group_layout,
+            bounds: &bounds_bind_group_layout,
+        };
+        let mut slots = Vec::with_capacity(max_chunks);
+        for i in 0..max_chunks {
+            let height_binding = BufferBinding {
+                buffer: &height_buffer,
+                offset: i as u64 * height_slot_bytes,
+                size: BufferSize::new((quality.height_grid_size() * quality.height_grid_size() * 4) as u64),
+            };
+            slots.push(Self::create_chunk_slot(device, &slot_layouts, &culler, height_binding, i));
         }
+        let bounds_readback = BoundsReadback::new(device, culler.chunk_buffer().size());
+        let height_readback = HeightReadback::new(device, height_slot_bytes);
+        let visible_bindings = if culler.multi_draw() {
+            vec![culler.visible_binding(None)]
+        } else {
+            (0..culler.lod_count()).map(|lod| culler.visible_binding(Some(lod))).collect()
+        };
+        let chunk_bind_groups = visible_bindings
+            .into_iter()
+            .enumerate()
+            .map(|(i, visible)| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some(&format!("Chunk Render Bind Group {}", i)),
+                    layout: &chunk_bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: culler.chunk_buffer().as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: height_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: BindingResource::Buffer(visible),
+                        },
+                        BindGroupEntry {
+                            binding: 3,
+                            resource: ao_buffer.as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect();
 
-        let mut renderer = Self {
+        Ok(Self {
             vertex_buffer,
             index_buffer,
-            index_count,
+            lods,
+            height_buffer,
+            height_slot_bytes,
+            ao_buffer,
+            ao_slot_bytes,
             slots,
             coord_to_slot: HashMap::new(),
             current_frame: 0,
             compute_pipeline,
+            bounds_pipeline,
+            ao_pipeline,
             render_pipeline,
-            _compute_bind_group_layout: compute_bind_group_layout,
+            compute_bind_group_layout,
+            ao_bind_group_layout,
+            erosion,
+            culler,
+            chunk_bind_groups,
+            shadows,
+            water,
+            bounds_readback,
+            height_readback,
+            chunks_generated: 0,
             camera_uniform_buffer,
             camera_bind_group,
             color_uniform_buffer,
+            colors_dirty: true,
+            sky_sun: None,
+            atmosphere: None,
             color_bind_group,
             settings,
+            quality,
+            surface_format,
             needs_regeneration: false,
-        };
-
-        // Generate initial chunks around origin
-        renderer.generate_initial_chunks(device, queue);
-
-        Ok(renderer)
+            settings_version: 0,
+            chunk_budget: DEFAULT_CHUNK_BUDGET,
+        })
     }
 
-    fn create_grid_buffers(device: &Device) -> (Buffer, Buffer, u32) {
+    fn create_grid_buffers(device: &Device, quality: &TerrainQuality) -> (Buffer, Buffer, Vec<LodRange>) {
+        let chunk_size = quality.chunk_size;
+
         // Create vertex buffer (UV coordinates)
-        let mut vertices = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE) as usize);
-        for z in 0..CHUNK_SIZE {
-            for x in 0..CHUNK_SIZE {
-                let u = x as f32 / (CHUNK_SIZE - 1) as f32;
-                let v = z as f32 / (CHUNK_SIZE - 1) as f32;
-                vertices.push(TerrainVertex { local_uv: [u, v] });
+        let mut vertices = Vec::with_capacity((chunk_size * chunk_size) as usize);
+        for z in 0..chunk_size {
+            for x in 0..chunk_size {
+                vertices.push(TerrainVertex::grid(chunk_size, x, z, 0.0));
             }
         }
 
-        let vertex_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
-            label: Some("Terrain Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: BufferUsages::VERTEX,
-        });
-
-        // Create index buffer
-        let mut indices: Vec<u32> = Vec::new();
-        for z in 0..(CHUNK_SIZE - 1) {
-            for x in 0..(CHUNK_SIZE - 1) {
-                let tl = z * CHUNK_SIZE + x;
-                let tr = tl + 1;
-                let bl = tl + CHUNK_SIZE;
-                let br = bl + 1;
-
-                // Two triangles per quad
-                indices.push(tl);
-                indices.push(bl);
-                indices.push(tr);
-                indices.push(tr);
-                indices.push(bl);
-                indices.push(br);
+        // Each level gets its own index range. Skirts hang down from the chunk
+        // edges to hide cracks against neighbours drawn at a coarser level, so
+        // they reach as deep as that level's sample spacing.
+        let strides = quality.lod_strides();
+        let mut lods = Vec::with_capacity(strides.len());
+        let mut all_indices = Vec::new();
+        for (lod, &stride) in strides.iter().enumerate() {
+            let coarser = strides[(lod + 1).min(strides.len() - 1)];
+            let skirt_depth = coarser as f32 * quality.sample_spacing();
+
+            let mut indices = grid_indices(chunk_size, stride);
+            for edge in edge_vertices(chunk_size, stride) {
+                let skirt_start = vertices.len() as u32;
+                vertices.extend(edge.iter().map(|&(x, z)| TerrainVertex::grid(chunk_size, x, z, skirt_depth)));
+
+                for i in 0..edge.len() - 1 {
+                    let (a, b) = (edge[i], edge[i + 1]);
+                    let top_a = a.1 * chunk_size + a.0;
+                    let top_b = b.1 * chunk_size + b.0;
+                    let bottom_a = skirt_start + i as u32;
+                    let bottom_b = bottom_a + 1;
+
+                    // Both windings, so the skirt is visible from either side
+                    indices.extend_from_slice(&[top_a, bottom_a, top_b, top_b, bottom_a, bottom_b]);
+                    indices.extend_from_slice(&[top_a, top_b, bottom_a, top_b, bottom_b, bottom_a]);
+                }
             }
+
+            lods.push(LodRange {
+                first_index: all_indices.len() as u32,
+                index_count: indices.len() as u32,
+            });
+            all_indices.extend(indices);
         }
 
         let index_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
             label: Some("Terrain Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
+            contents: bytemuck::cast_slice(&all_indices),
             usage: BufferUsages::INDEX,
         });
 
-        (vertex_buffer, index_buffer, indices.len() as u32)
+        let vertex_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Terrain Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+
+        (vertex_buffer, index_buffer, lods)
     }
 
     fn create_chunk_slot(
         device: &Device,
-        chunk_bind_group_layout: &BindGroupLayout,
-        compute_bind_group_layout: &BindGroupLayout,
+        layouts: &ChunkSlotLayouts,
+        culler: &ChunkCuller,
+        height_binding: BufferBinding,
         index: usize,
     ) -> ChunkSlot {
-        let height_count = CHUNK_SIZE * CHUNK_SIZE;
-
         let params_buffer = device.create_buffer(&BufferDescriptor {
             label: Some(&format!("Chunk {} Params Buffer", index)),
             size: std::mem::size_of::<ComputeParams>() as u64,
@@ -552,38 +1241,24 @@ impl TerrainRenderer {
             mapped_at_creation: false,
         });
 
-        let height_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some(&format!("Chunk {} Height Buffer", index)),
-            size: (height_count * 4) as u64, // f32 per vertex
-            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let uniform_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some(&format!("Chunk {} Uniform Buffer", index)),
-            size: std::mem::size_of::<ChunkUniform>() as u64,
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let render_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some(&format!("Chunk {} Render Bind Group", index)),
-            layout: chunk_bind_group_layout,
+        let compute_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("Chunk {} Compute Bind Group", index)),
+            layout: layouts.compute,
             entries: &[
                 BindGroupEntry {
                     binding: 0,
-                    resource: uniform_buffer.as_entire_binding(),
+                    resource: params_buffer.as_entire_binding(),
                 },
                 BindGroupEntry {
                     binding: 1,
-                    resource: height_buffer.as_entire_binding(),
+                    resource: BindingResource::Buffer(height_binding.clone()),
                 },
             ],
         });
 
-        let compute_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some(&format!("Chunk {} Compute Bind Group", index)),
-            layout: compute_bind_group_layout,
+        let bounds_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("Chunk {} Height Bounds Bind Group", index)),
+            layout: layouts.bounds,
             entries: &[
                 BindGroupEntry {
                     binding: 0,
@@ -591,7 +1266,11 @@ impl TerrainRenderer {
                 },
                 BindGroupEntry {
                     binding: 1,
-                    resource: height_buffer.as_entire_binding(),
+                    resource: BindingResource::Buffer(height_binding.clone()),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: culler.chunk_buffer().as_entire_binding(),
                 },
             ],
         });
@@ -600,41 +1279,94 @@ impl TerrainRenderer {
             state: ChunkState::Empty,
             coord: None,
             params_buffer,
-            _height_buffer: height_buffer,
-            uniform_buffer,
             compute_bind_group,
-            render_bind_group,
+            bounds_bind_group,
+            scratch_bind_groups: None,
+            height_bounds: None,
+            heights: None,
+            generation: 0,
+            settings_version: 0,
             last_used_frame: 0,
         }
     }
 
-    fn generate_initial_chunks(&mut self, device: &Device, queue: &Queue) {
-        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("Initial Chunks Encoder"),
+    /// Bind a slot's params, height range and AO range to the erosion scratch grid.
+    /// None until the scratch grid has been reserved.
+    fn create_scratch_bind_groups(&self, device: &Device, slot_idx: usize) -> Option<ScratchBindGroups> {
+        let scratch_heights = self.erosion.scratch_heights()?;
+        let params_buffer = &self.slots[slot_idx].params_buffer;
+        let grid_size = self.quality.height_grid_size() as u64;
+        let height_binding = BufferBinding {
+            buffer: &self.height_buffer,
+            offset: slot_idx as u64 * self.height_slot_bytes,
+            size: BufferSize::new(grid_size * grid_size * 4),
+        };
+        let ao_bytes = (self.quality.chunk_size * self.quality.chunk_size * 4) as u64;
+        let ao_binding = BufferBinding {
+            buffer: &self.ao_buffer,
+            offset: slot_idx as u64 * self.ao_slot_bytes,
+            size: BufferSize::new(ao_bytes),
+        };
+
+        let compute = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("Chunk {} Scratch Compute Bind Group", slot_idx)),
+            layout: &self.compute_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: scratch_heights.as_entire_binding(),
+                },
+            ],
         });
 
-        for dz in -VIEW_RADIUS..=VIEW_RADIUS {
-            for dx in -VIEW_RADIUS..=VIEW_RADIUS {
-                let coord = ChunkCoord::new(dx, dz);
-                self.generate_chunk(queue, &mut encoder, coord);
-            }
-        }
+        let ao = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("Chunk {} AO Bind Group", slot_idx)),
+            layout: &self.ao_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: scratch_heights.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Buffer(ao_binding),
+                },
+            ],
+        });
 
-        queue.submit(std::iter::once(encoder.finish()));
-        log::info!(
-            "Generated {} initial chunks",
-            (VIEW_RADIUS * 2 + 1) * (VIEW_RADIUS * 2 + 1)
-        );
+        let erosion = self.erosion.create_bind_group(device, params_buffer, height_binding, slot_idx)?;
+        Some(ScratchBindGroups { compute, erosion, ao })
     }
 
     fn generate_chunk(
         &mut self,
+        device: &Device,
         queue: &Queue,
         encoder: &mut CommandEncoder,
         coord: ChunkCoord,
+        slot_idx: usize,
     ) {
-        // Find a free slot or recycle LRU
-        let slot_idx = self.get_free_slot();
+        // Padded chunks go through the shared scratch grid, sized for the current border
+        let border = generation_border(&self.settings, &self.quality);
+        if border != HEIGHT_APRON {
+            if self.erosion.reserve_scratch(device, border) {
+                // Bind groups over the old scratch buffers are stale
+                for slot in &mut self.slots {
+                    slot.scratch_bind_groups = None;
+                }
+            }
+            if self.slots[slot_idx].scratch_bind_groups.is_none() {
+                self.slots[slot_idx].scratch_bind_groups = self.create_scratch_bind_groups(device, slot_idx);
+            }
+        }
 
         // Remove old mapping if recycling
         if let Some(old_coord) = self.slots[slot_idx].coord {
@@ -642,36 +1374,41 @@ impl TerrainRenderer {
         }
 
         // Setup slot
+        self.chunks_generated += 1;
         let slot = &mut self.slots[slot_idx];
         slot.state = ChunkState::Ready;
         slot.coord = Some(coord);
+        slot.height_bounds = None;
+        slot.heights = None;
+        slot.generation = self.chunks_generated;
+        slot.settings_version = self.settings_version;
         slot.last_used_frame = self.current_frame;
 
         self.coord_to_slot.insert(coord, slot_idx);
 
-        // Update chunk uniform
-        let chunk_uniform = ChunkUniform {
-            chunk_offset: coord.world_offset(),
-            _padding: [0.0, 0.0],
+        // Update the slot's render data
+        let chunk_info = ChunkInfo {
+            world_offset: coord.world_offset(&self.quality),
+            height_offset: (slot_idx as u64 * self.height_slot_bytes / 4) as u32,
+            ready: 1,
+            ao_offset: (slot_idx as u64 * self.ao_slot_bytes / 4) as u32,
+            ..Default::default()
         };
-        queue.write_buffer(&slot.uniform_buffer, 0, bytemuck::cast_slice(&[chunk_uniform]));
+        self.culler.write_chunk(queue, slot_idx, chunk_info);
 
         // Dispatch compute shader
-        let compute_params = ComputeParams {
-            chunk_offset: coord.world_offset(),
-            terrain_scale: self.settings.terrain_scale,
-            height_scale: self.settings.height_scale,
-            octaves: self.settings.octaves,
-            warp_strength: self.settings.warp_strength,
-            height_variance: self.settings.height_variance,
-            roughness: self.settings.roughness,
-            pattern_type: self.settings.pattern_type,
-            seed: self.settings.seed,
-            _padding: [0.0, 0.0],
-        };
+        let bake_ao = ao_border(&self.settings, &self.quality) > 0;
+        let compute_params = ComputeParams::new(&self.settings, &self.quality, coord, border).with_slot(slot_idx);
 
         queue.write_buffer(&slot.params_buffer, 0, bytemuck::cast_slice(&[compute_params]));
 
+        if !bake_ao {
+            // Fully open sky
+            let offset = slot_idx as u64 * self.ao_slot_bytes;
+            let size = (self.quality.chunk_size * self.quality.chunk_size * 4) as u64;
+            encoder.clear_buffer(&self.ao_buffer, offset, Some(size));
+        }
+
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("Height Compute Pass"),
@@ -679,10 +1416,35 @@ impl TerrainRenderer {
             });
 
             compute_pass.set_pipeline(&self.compute_pipeline);
-            compute_pass.set_bind_group(0, &slot.compute_bind_group, &[]);
+            if border == HEIGHT_APRON {
+                compute_pass.set_bind_group(0, &slot.compute_bind_group, &[]);
+
+                let workgroups = self.quality.height_grid_size().div_ceil(TERRAIN_WORKGROUP_SIZE);
+                compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+            } else if let Some(scratch) = &slot.scratch_bind_groups {
+                // Generate the padded grid into scratch, then erode it into the slot
+                compute_pass.set_bind_group(0, &scratch.compute, &[]);
+
+                let workgroups = self.erosion.padded_size(border).div_ceil(TERRAIN_WORKGROUP_SIZE);
+                compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+
+                self.erosion
+                    .dispatch(&mut compute_pass, &scratch.erosion, &self.settings);
 
-            let workgroups = (CHUNK_SIZE + TERRAIN_WORKGROUP_SIZE - 1) / TERRAIN_WORKGROUP_SIZE;
-            compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+                // Bake AO from the eroded grid while its border is still there
+                if bake_ao {
+                    compute_pass.set_pipeline(&self.ao_pipeline);
+                    compute_pass.set_bind_group(0, &scratch.ao, &[]);
+
+                    let workgroups = self.quality.chunk_size.div_ceil(TERRAIN_WORKGROUP_SIZE);
+                    compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+                }
+            }
+
+            // Reduce the final heights to the chunk's min/max for culling
+            compute_pass.set_pipeline(&self.bounds_pipeline);
+            compute_pass.set_bind_group(0, &slot.bounds_bind_group, &[]);
+            compute_pass.dispatch_workgroups(1, 1, 1);
         }
     }
 
@@ -706,62 +1468,215 @@ impl TerrainRenderer {
         oldest_idx
     }
 
-    pub fn update(&mut self, device: &Device, queue: &Queue, camera_pos: Vec3) {
+    /// Stream chunks around the camera, generating at most the chunk budget this frame,
+    /// and advance the water animation by `dt` seconds
+    pub fn update(&mut self, device: &Device, queue: &Queue, camera: &dyn CameraController, dt: f32) {
+        self.water.update(dt);
+        if self.colors_dirty {
+            self.write_color_params(queue);
+        }
+        self.stream_chunks(device, queue, camera, self.chunk_budget);
+    }
+
+    /// Generate every missing or stale chunk around the camera now, ignoring the budget.
+    /// For offline rendering, where each frame must show finished terrain.
+    pub fn finish_streaming(&mut self, device: &Device, queue: &Queue, camera: &dyn CameraController) {
+        self.stream_chunks(device, queue, camera, usize::MAX);
+    }
+
+    /// Maximum chunks generated per frame by `update`
+    pub fn set_chunk_budget(&mut self, budget: usize) {
+        self.chunk_budget = budget.max(1);
+    }
+
+    fn stream_chunks(&mut self, device: &Device, queue: &Queue, camera: &dyn CameraController, budget: usize) {
         self.current_frame += 1;
 
-        let camera_chunk = ChunkCoord::from_world_pos(camera_pos);
+        let camera_chunk = ChunkCoord::from_world_pos(camera.position(), &self.quality);
+        let frustum_planes = camera.extract_frustum_planes();
+        // Rough vertical extent for chunks whose bounds aren't known yet; only affects priority
+        let guess = HeightBounds {
+            min: -self.settings.height_scale,
+            max: self.settings.height_scale * 2.0,
+        };
 
-        // Determine needed chunks
-        let visible_span = (VIEW_RADIUS * 2 + 1) as usize;
-        let mut needed_chunks = Vec::with_capacity(visible_span * visible_span);
-        for dz in -VIEW_RADIUS..=VIEW_RADIUS {
-            for dx in -VIEW_RADIUS..=VIEW_RADIUS {
+        // Find missing and stale chunks, marking the rest as used. Stale chunks
+        // keep being drawn until they are regenerated in place.
+        let radius = self.quality.view_radius;
+        let mut queue_entries = Vec::new();
+        for dz in -radius..=radius {
+            for dx in -radius..=radius {
                 let coord = ChunkCoord::new(camera_chunk.x + dx, camera_chunk.z + dz);
-                needed_chunks.push(coord);
+                let slot_idx = self.coord_to_slot.get(&coord).copied();
+                if let Some(slot_idx) = slot_idx {
+                    let slot = &mut self.slots[slot_idx];
+                    slot.last_used_frame = self.current_frame;
+                    if slot.settings_version == self.settings_version {
+                        continue;
+                    }
+                }
+
+                // Chunks in view first, then nearest to the camera
+                let bounds = slot_idx.and_then(|i| self.slots[i].height_bounds).unwrap_or(guess);
+                let hidden = !coord.is_visible_in_frustum(&frustum_planes, bounds, &self.quality);
+                queue_entries.push((hidden, dx * dx + dz * dz, coord, slot_idx));
             }
         }
+        if queue_entries.is_empty() {
+            self.poll_height_bounds(device, queue);
+            self.poll_heights(device, queue, camera_chunk);
+            return;
+        }
 
-        // Mark existing chunks as used
-        for coord in &needed_chunks {
-            if let Some(&slot_idx) = self.coord_to_slot.get(coord) {
-                self.slots[slot_idx].last_used_frame = self.current_frame;
-            }
+        let count = queue_entries.len().min(budget);
+        if count < queue_entries.len() {
+            queue_entries.select_nth_unstable_by_key(count, |&(hidden, distance, _, _)| (hidden, distance));
         }
 
-        // Generate missing chunks
-        let mut encoder: Option<CommandEncoder> = None;
-        for coord in needed_chunks {
-            if !self.coord_to_slot.contains_key(&coord) {
-                let encoder_ref = encoder.get_or_insert_with(|| {
-                    device.create_command_encoder(&CommandEncoderDescriptor {
-                        label: Some("Chunk Update Encoder"),
-                    })
-                });
-                self.generate_chunk(queue, encoder_ref, coord);
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Chunk Update Encoder"),
+        });
+        for &(_, _, coord, slot_idx) in &queue_entries[..count] {
+            // Stale chunks are regenerated in their own slot, missing ones recycle the LRU slot
+            let slot_idx = slot_idx.unwrap_or_else(|| self.get_free_slot());
+            self.generate_chunk(device, queue, &mut encoder, coord, slot_idx);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        self.poll_height_bounds(device, queue);
+        self.poll_heights(device, queue, camera_chunk);
+    }
+
+    /// Store finished height bounds readbacks in their slots, then start
+    /// another readback if any ready chunk is still missing its bounds
+    fn poll_height_bounds(&mut self, device: &Device, queue: &Queue) {
+        device.poll(Maintain::Poll);
+
+        if let Some((generations, receiver)) = self.bounds_readback.pending.take() {
+            match receiver.try_recv() {
+                Ok(Ok(())) => {
+                    {
+                        let data = self.bounds_readback.staging.slice(..).get_mapped_range();
+                        let infos: &[ChunkInfo] = bytemuck::cast_slice(&data);
+                        for ((slot, info), generation) in self.slots.iter_mut().zip(infos).zip(generations) {
+                            // Skip slots regenerated since the copy was recorded
+                            if slot.state == ChunkState::Ready && slot.generation == generation {
+                                slot.height_bounds = Some(HeightBounds {
+                                    min: info.min_height,
+                                    max: info.max_height,
+                                });
+                            }
+                        }
+                    }
+                    self.bounds_readback.staging.unmap();
+                }
+                Ok(Err(e)) => log::warn!("Height bounds readback failed: {}", e),
+                Err(mpsc::TryRecvError::Empty) => {
+                    self.bounds_readback.pending = Some((generations, receiver));
+                    return;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {}
             }
         }
 
-        if let Some(encoder) = encoder {
-            queue.submit(std::iter::once(encoder.finish()));
+        let missing = self
+            .slots
+            .iter()
+            .any(|slot| slot.state == ChunkState::Ready && slot.height_bounds.is_none());
+        if !missing {
+            return;
         }
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Height Bounds Readback Encoder"),
+        });
+        let staging = &self.bounds_readback.staging;
+        encoder.copy_buffer_to_buffer(self.culler.chunk_buffer(), 0, staging, 0, staging.size());
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        staging.slice(..).map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        let generations = self.slots.iter().map(|slot| slot.generation).collect();
+        self.bounds_readback.pending = Some((generations, receiver));
     }
 
-    pub fn render(
-        &self,
-        encoder: &mut CommandEncoder,
-        color_view: &TextureView,
-        depth_view: &TextureView,
-        camera: &FlyCamera,
-        queue: &Queue,
-    ) {
-        // Update camera uniform
-        queue.write_buffer(
-            &self.camera_uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[camera.uniform_data()]),
-        );
+    /// Store finished height readbacks in their slots, then copy back the
+    /// ready chunks nearest the camera that are still missing their heights
+    fn poll_heights(&mut self, device: &Device, queue: &Queue, camera_chunk: ChunkCoord) {
+        if let Some((copied, receiver)) = self.height_readback.pending.take() {
+            match receiver.try_recv() {
+                Ok(Ok(())) => {
+                    {
+                        let mapped = copied.len() as u64 * self.height_slot_bytes;
+                        let data = self.height_readback.staging.slice(..mapped).get_mapped_range();
+                        let grid_size = self.quality.height_grid_size() as usize;
+                        for (i, &(slot_idx, generation)) in copied.iter().enumerate() {
+                            // Skip slots regenerated since the copy was recorded
+                            let slot = &mut self.slots[slot_idx];
+                            if slot.state != ChunkState::Ready || slot.generation != generation {
+                                continue;
+                            }
+                            let start = i * self.height_slot_bytes as usize;
+                            let grid: &[f32] = bytemuck::cast_slice(&data[start..start + grid_size * grid_size * 4]);
+                            slot.heights = Some(grid.to_vec());
+                        }
+                    }
+                    self.height_readback.staging.unmap();
+                }
+                Ok(Err(e)) => log::warn!("Height readback failed: {}", e),
+                Err(mpsc::TryRecvError::Empty) => {
+                    self.height_readback.pending = Some((copied, receiver));
+                    return;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {}
+            }
+        }
+
+        let mut missing: Vec<(i32, usize)> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.state == ChunkState::Ready && slot.heights.is_none())
+            .filter_map(|(i, slot)| {
+                let coord = slot.coord?;
+                let (dx, dz) = (coord.x - camera_chunk.x, coord.z - camera_chunk.z);
+                Some((dx * dx + dz * dz, i))
+            })
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        let count = missing.len().min(HEIGHT_READBACK_CHUNKS);
+        if count < missing.len() {
+            missing.select_nth_unstable(count);
+        }
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Height Readback Encoder"),
+        });
+        let staging = &self.height_readback.staging;
+        let bytes = self.height_slot_bytes;
+        for (i, &(_, slot_idx)) in missing[..count].iter().enumerate() {
+            encoder.copy_buffer_to_buffer(&self.height_buffer, slot_idx as u64 * bytes, staging, i as u64 * bytes, bytes);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        staging.slice(..count as u64 * bytes).map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        let copied = missing[..count]
+            .iter()
+            .map(|&(_, slot_idx)| (slot_idx, self.slots[slot_idx].generation))
+            .collect();
+        self.height_readback.pending = Some((copied, receiver));
+    }
 
-        // Update color uniform
+    fn write_color_params(&mut self, queue: &Queue) {
+        let sun = self.sun_light();
+        let atmosphere = self.atmosphere();
         let color_params = ColorParams {
             color_abyss: rgb_to_rgba(self.settings.color_abyss),
             color_deep_water: rgb_to_rgba(self.settings.color_deep_water),
@@ -771,12 +1686,17 @@ impl TerrainRenderer {
             color_rock: rgb_to_rgba(self.settings.color_rock),
             color_snow: rgb_to_rgba(self.settings.color_snow),
             color_sky: rgb_to_rgba(self.settings.color_sky),
-            color_sky_top: rgb_to_rgba(self.settings.color_sky_top),
-            color_sky_horizon: rgb_to_rgba(self.settings.color_sky_horizon),
-            ambient: self.settings.ambient,
-            fog_start: self.settings.fog_start,
-            fog_distance: self.settings.fog_distance,
-            _padding: 0.0,
+            color_sky_top: rgb_to_rgba(atmosphere.sky_top),
+            color_sky_horizon: rgb_to_rgba(atmosphere.sky_horizon),
+            sun_direction: sun.direction.extend(0.0).to_array(),
+            sun_color: (Vec3::from(sun.color) * self.settings.sun_intensity).extend(0.0).to_array(),
+            ambient: atmosphere.ambient,
+            fog_start: atmosphere.fog_start,
+            fog_distance: atmosphere.fog_distance,
+            ao_strength: self.settings.ao_strength,
+            sea_level: self.settings.sea_level,
+            water_enabled: self.settings.water_enabled as u32,
+            _padding: [0.0; 2],
         };
         queue.write_buffer(
             &self.color_uniform_buffer,
@@ -784,6 +1704,36 @@ impl TerrainRenderer {
             bytemuck::cast_slice(&[color_params]),
         );
 
+        self.colors_dirty = false;
+    }
+
+    pub fn render(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        color_view: &TextureView,
+        depth_view: &TextureView,
+        camera: &dyn CameraController,
+        queue: &Queue,
+    ) {
+        let sky_horizon = self.atmosphere().sky_horizon;
+
+        // Update camera uniform
+        queue.write_buffer(
+            &self.camera_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[camera.uniform_data()]),
+        );
+
+        // Shadow depth passes from the light, then cull chunks on the GPU against their
+        // height bounds, filling the indirect draw arguments
+        self.shadows
+            .render(encoder, queue, camera, self.sun_light().direction, |render_pass, cascade| {
+                self.draw_shadow_casters(render_pass, cascade)
+            });
+        self.culler
+            .cull(encoder, queue, &camera.extract_frustum_planes(), camera.position());
+
         {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("Terrain Render Pass"),
@@ -792,9 +1742,9 @@ impl TerrainRenderer {
                     resolve_target: None,
                     ops: Operations {
                         load: LoadOp::Clear(Color {
-                            r: self.settings.color_sky_horizon[0] as f64,
-                            g: self.settings.color_sky_horizon[1] as f64,
-                            b: self.settings.color_sky_horizon[2] as f64,
+                            r: sky_horizon[0] as f64,
+                            g: sky_horizon[1] as f64,
+                            b: sky_horizon[2] as f64,
                             a: 1.0,
                         }),
                         store: StoreOp::Store,
@@ -815,34 +1765,219 @@ impl TerrainRenderer {
             render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
             render_pass.set_bind_group(2, &self.color_bind_group, &[]);
+            render_pass.set_bind_group(3, self.shadows.sample_bind_group(), &[]);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint32);
+            self.culler.draw(&mut render_pass, 1, &self.chunk_bind_groups);
+        }
 
-            // Extract frustum planes for culling
-            let frustum_planes = camera.extract_frustum_planes();
-            let height_scale = self.settings.height_scale;
-
-            // Draw only visible chunks (frustum culling)
-            for slot in &self.slots {
-                if slot.state == ChunkState::Ready {
-                    if let Some(coord) = slot.coord {
-                        // Skip chunks outside the camera frustum
-                        if !coord.is_visible_in_frustum(&frustum_planes, height_scale) {
-                            continue;
-                        }
-                    }
-                    render_pass.set_bind_group(1, &slot.render_bind_group, &[]);
-                    render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+        if self.settings.water_enabled {
+            // Cover the loaded chunks around the camera
+            let extent = (self.quality.view_radius + 1) as f32 * self.quality.chunk_world_size;
+            self.water.write_params(queue, camera, &self.settings, extent);
+            self.water.render(
+                device,
+                encoder,
+                color_view,
+                depth_view,
+                &self.camera_bind_group,
+                &self.color_bind_group,
+            );
+        }
+    }
+
+    /// Draw the ready chunks that can cast into a shadow cascade, at the level
+    /// of detail of the cascade's nearest receivers. Consecutive slots share a
+    /// draw call; `vs_shadow` reads each chunk by its slot as the instance index.
+    fn draw_shadow_casters(&self, render_pass: &mut RenderPass<'_>, cascade: &ShadowCascade) {
+        let near_chunks = cascade.near_depth / self.quality.chunk_world_size;
+        let lod = LOD_DISTANCES.iter().filter(|&&d| near_chunks > d).count();
+        let lod = self.lods[lod.min(self.lods.len() - 1)];
+        let indices = lod.first_index..lod.first_index + lod.index_count;
+
+        render_pass.set_bind_group(1, &self.chunk_bind_groups[0], &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint32);
+
+        // Unknown bounds get the same rough extent streaming uses
+        let guess = HeightBounds {
+            min: -self.settings.height_scale,
+            max: self.settings.height_scale * 2.0,
+        };
+        let planes = frustum_planes(cascade.view_proj);
+        let mut run_start = None;
+        for (i, slot) in self.slots.iter().enumerate() {
+            let casts = slot.state == ChunkState::Ready
+                && slot.coord.is_some_and(|coord| {
+                    coord.is_visible_in_frustum(&planes, slot.height_bounds.unwrap_or(guess), &self.quality)
+                });
+            match (casts, run_start) {
+                (true, None) => run_start = Some(i as u32),
+                (false, Some(start)) => {
+                    render_pass.draw_indexed(indices.clone(), 0, start..i as u32);
+                    run_start = None;
                 }
+                _ => {}
             }
         }
+        if let Some(start) = run_start {
+            render_pass.draw_indexed(indices, 0, start..self.slots.len() as u32);
+        }
     }
 
-    /// Update terrain settings and mark for regeneration
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        self.shadows.settings()
+    }
+
+    /// Toggle shadows or change their resolution, cascades or distance
+    pub fn update_shadow_settings(&mut self, device: &Device, settings: ShadowSettings) -> Result<(), String> {
+        self.shadows.update_settings(device, settings)
+    }
+
+    /// Update terrain settings. Only geometry changes queue regeneration;
+    /// appearance changes just rewrite the color uniform.
     pub fn update_settings(&mut self, settings: TerrainSettings) {
+        if self.settings == settings {
+            return;
+        }
+
+        let regenerate = !self.settings.same_geometry(&settings);
         self.settings = settings;
-        self.needs_regeneration = true;
-        log::info!("Terrain settings updated, regeneration queued");
+        self.colors_dirty = true;
+        if regenerate {
+            warn_if_iterations_clamped(&self.settings);
+            self.needs_regeneration = true;
+            log::info!("Terrain settings updated, regeneration queued");
+        } else {
+            log::info!("Terrain appearance updated");
+        }
+    }
+
+    /// Track the sky's highest sun, rewriting the color uniform when it changes
+    pub fn set_sky_sun(&mut self, sun: Option<SunLight>) {
+        if self.sky_sun != sun {
+            self.sky_sun = sun;
+            self.colors_dirty = true;
+        }
+    }
+
+    /// Override the sky colors, ambient light and fog (from the day/night
+    /// cycle), or go back to the settings with `None`
+    pub fn set_atmosphere(&mut self, atmosphere: Option<Atmosphere>) {
+        if self.atmosphere != atmosphere {
+            self.atmosphere = atmosphere;
+            self.colors_dirty = true;
+        }
+    }
+
+    /// Sky colors, ambient light and fog in use: the override if set, otherwise the settings
+    pub fn atmosphere(&self) -> Atmosphere {
+        self.atmosphere.unwrap_or(Atmosphere {
+            sky_top: self.settings.color_sky_top,
+            sky_horizon: self.settings.color_sky_horizon,
+            ambient: self.settings.ambient,
+            fog_start: self.settings.fog_start,
+            fog_distance: self.settings.fog_distance,
+        })
+    }
+
+    /// Light used for shading: the sky's sun if enabled and present, otherwise the settings
+    pub fn sun_light(&self) -> SunLight {
+        match self.sky_sun {
+            Some(sun) if self.settings.sun_from_sky => sun,
+            _ => self.settings_sun(),
+        }
+    }
+
+    /// Light from the sun direction and color settings
+    pub fn settings_sun(&self) -> SunLight {
+        SunLight {
+            direction: Vec3::from(self.settings.sun_direction).normalize_or(Vec3::Y),
+            color: self.settings.sun_color,
+        }
+    }
+
+    pub fn quality(&self) -> &TerrainQuality {
+        &self.quality
+    }
+
+    /// Height bounds of the loaded chunk under a world XZ position. Available a
+    /// few frames after the chunk is generated, once read back from the GPU.
+    pub fn height_bounds_at(&self, x: f32, z: f32) -> Option<HeightBounds> {
+        let coord = ChunkCoord::from_world_pos(Vec3::new(x, 0.0, z), &self.quality);
+        let &slot_idx = self.coord_to_slot.get(&coord)?;
+        self.slots[slot_idx].height_bounds
+    }
+
+    /// Terrain height at a world XZ position, interpolated bilinearly from the heights
+    /// generated on the GPU (erosion included). None until the chunk under it has been
+    /// generated and read back, a few frames later.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let coord = ChunkCoord::from_world_pos(Vec3::new(x, 0.0, z), &self.quality);
+        let heights = self.chunk_heights(coord)?;
+
+        let size = self.quality.chunk_size as usize;
+        let grid_size = self.quality.height_grid_size() as usize;
+        let apron = HEIGHT_APRON as usize;
+        let [offset_x, offset_z] = coord.world_offset(&self.quality);
+        let last = (size - 1) as f32;
+        let spacing = self.quality.sample_spacing();
+        let gx = ((x - offset_x) / spacing).clamp(0.0, last);
+        let gz = ((z - offset_z) / spacing).clamp(0.0, last);
+        let (x0, z0) = ((gx as usize).min(size - 2), (gz as usize).min(size - 2));
+        let (tx, tz) = (gx - x0 as f32, gz - z0 as f32);
+
+        let height = |x: usize, z: usize| heights[(z + apron) * grid_size + x + apron];
+        let near = height(x0, z0) + (height(x0 + 1, z0) - height(x0, z0)) * tx;
+        let far = height(x0, z0 + 1) + (height(x0 + 1, z0 + 1) - height(x0, z0 + 1)) * tx;
+        Some(near + (far - near) * tz)
+    }
+
+    /// Heights of a loaded chunk as generated on the GPU (erosion included), in the
+    /// layout of `TerrainSampler::sample_grid` with `HEIGHT_APRON`. None until read back.
+    pub fn chunk_heights(&self, coord: ChunkCoord) -> Option<&[f32]> {
+        let &slot_idx = self.coord_to_slot.get(&coord)?;
+        self.slots[slot_idx].heights.as_deref()
+    }
+
+    /// Read back the heights of every generated chunk, blocking until they arrive.
+    /// `update` does this over the following frames; native tools such as the
+    /// exporters call it after `finish_streaming` instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn finish_height_readback(&mut self, device: &Device, queue: &Queue) {
+        // Each pass stores the previous copy and starts the next; bounded in case mapping fails
+        let passes = self.slots.len().div_ceil(HEIGHT_READBACK_CHUNKS) + 1;
+        for _ in 0..passes {
+            self.poll_heights(device, queue, ChunkCoord::new(0, 0));
+            if self.height_readback.pending.is_none() {
+                break;
+            }
+            device.poll(Maintain::Wait);
+        }
+    }
+
+    /// Rebuild the chunk pool, shared geometry and pipelines for a new quality level.
+    /// The new pool starts empty and chunks stream back in around the camera.
+    pub fn set_quality(&mut self, device: &Device, quality: TerrainQuality) -> Result<(), String> {
+        if quality == self.quality {
+            return Ok(());
+        }
+
+        let mut renderer = Self::build(
+            device,
+            self.surface_format,
+            self.settings.clone(),
+            quality,
+            *self.shadows.settings(),
+        )?;
+        renderer.chunk_budget = self.chunk_budget;
+        *self = renderer;
+        log::info!(
+            "Terrain quality set to {} vertices per chunk, view radius {}",
+            quality.chunk_size,
+            quality.view_radius
+        );
+        Ok(())
     }
 
     /// Queue terrain regeneration with current settings (e.g., from R key)
@@ -851,38 +1986,251 @@ impl TerrainRenderer {
         log::info!("Terrain regeneration queued");
     }
 
-    /// Regenerate all chunks with current settings
-    pub fn regenerate_all_chunks(&mut self, device: &Device, queue: &Queue, camera_pos: Vec3) {
-        // Clear all chunks
-        for slot in &mut self.slots {
-            slot.state = ChunkState::Empty;
-            slot.coord = None;
+    /// Mark every loaded chunk stale if regeneration was queued. Stale chunks
+    /// stay visible and are regenerated in place by `update` within the budget.
+    pub fn check_regeneration(&mut self) {
+        if self.needs_regeneration {
+            self.settings_version += 1;
+            self.needs_regeneration = false;
+            log::info!("Terrain chunks marked for regeneration");
         }
-        self.coord_to_slot.clear();
+    }
+}
 
-        // Generate chunks around camera position
-        let camera_chunk = ChunkCoord::from_world_pos(camera_pos);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lod_grids_use_only_their_own_vertices() {
+        for chunk_size in [64, 33, 97, 128, 8] {
+            let quality = TerrainQuality {
+                chunk_size,
+                ..Default::default()
+            };
+            for stride in quality.lod_strides() {
+                assert_eq!((chunk_size - 1) % stride, 0);
+                let quads = (chunk_size - 1) / stride;
+                let indices = grid_indices(chunk_size, stride);
+                assert_eq!(indices.len() as u32, quads * quads * 6);
+                for i in indices {
+                    let (x, z) = (i % chunk_size, i / chunk_size);
+                    assert!(x % stride == 0 && z % stride == 0);
+                }
+            }
+        }
+        assert_eq!(TerrainQuality::default().lod_strides(), LOD_STRIDES);
+    }
 
-        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
-            label: Some("Regenerate Chunks Encoder"),
-        });
+    #[test]
+    fn ultra_quality_fits_shared_height_buffer() {
+        // Mirrors the web quality selector's ultra level
+        let ultra = TerrainQuality {
+            chunk_size: 97,
+            chunk_world_size: 256.0,
+            view_radius: 24,
+        };
+        assert!(ultra.validate().is_ok());
+        assert!(TerrainQuality { chunk_size: 128, ..ultra }.validate().is_err());
+    }
 
-        for dz in -VIEW_RADIUS..=VIEW_RADIUS {
-            for dx in -VIEW_RADIUS..=VIEW_RADIUS {
-                let coord = ChunkCoord::new(camera_chunk.x + dx, camera_chunk.z + dz);
-                self.generate_chunk(queue, &mut encoder, coord);
+    #[test]
+    fn neighbouring_chunks_differ_by_at_most_one_lod() {
+        let quality = TerrainQuality::default();
+        let camera = Vec3::new(37.0, 80.0, -1210.0);
+        let center = ChunkCoord::from_world_pos(camera, &quality);
+        for dz in -16..=16 {
+            for dx in -16..=16 {
+                let coord = ChunkCoord::new(center.x + dx, center.z + dz);
+                let lod = coord.lod_for(camera, &quality);
+                for neighbour in [ChunkCoord::new(coord.x + 1, coord.z), ChunkCoord::new(coord.x, coord.z + 1)] {
+                    assert!(lod.abs_diff(neighbour.lod_for(camera, &quality)) <= 1);
+                }
             }
         }
+    }
 
-        queue.submit(std::iter::once(encoder.finish()));
-        self.needs_regeneration = false;
-        log::info!("Regenerated all terrain chunks");
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn height_bounds_match_cpu_samples() {
+        use crate::sampler::TerrainSampler;
+        use crate::camera::FlyCamera;
+        use crate::webgpu::test_gpu;
+
+        let gpu = test_gpu();
+        let settings = TerrainSettings::default();
+        let quality = TerrainQuality {
+            view_radius: 2,
+            ..Default::default()
+        };
+        let mut terrain =
+            TerrainRenderer::new(&gpu.device, gpu.surface_format, settings.clone(), quality).unwrap();
+
+        // Fill the pool, then the first update starts the readback and the second collects it
+        let camera = FlyCamera::new(1.0);
+        terrain.finish_streaming(&gpu.device, &gpu.queue, &camera);
+        for _ in 0..2 {
+            terrain.update(&gpu.device, &gpu.queue, &camera, 0.0);
+            gpu.device.poll(Maintain::Wait);
+        }
+
+        let sampler = TerrainSampler::new(&settings).with_quality(quality);
+        let tolerance = 1e-3 * settings.height_scale;
+        for slot in &terrain.slots {
+            let bounds = slot.height_bounds.expect("bounds should be read back");
+            let heights = sampler.sample_chunk(slot.coord.unwrap());
+            let min = heights.iter().copied().fold(f32::MAX, f32::min);
+            let max = heights.iter().copied().fold(f32::MIN, f32::max);
+            assert!((bounds.min - min).abs() <= tolerance, "{:?} vs {}", bounds, min);
+            assert!((bounds.max - max).abs() <= tolerance, "{:?} vs {}", bounds, max);
+        }
+
+        let center = terrain.coord_to_slot[&ChunkCoord::new(0, 0)];
+        assert_eq!(terrain.height_bounds_at(10.0, 200.0), terrain.slots[center].height_bounds);
     }
 
-    /// Check if regeneration is needed and perform it
-    pub fn check_regeneration(&mut self, device: &Device, queue: &Queue, camera_pos: Vec3) {
-        if self.needs_regeneration {
-            self.regenerate_all_chunks(device, queue, camera_pos);
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn height_queries_use_read_back_heights() {
+        use crate::camera::FlyCamera;
+        use crate::sampler::{TerrainSampler, GPU_HEIGHT_TOLERANCE};
+        use crate::webgpu::test_gpu;
+
+        let gpu = test_gpu();
+        let settings = TerrainSettings::default();
+        let quality = TerrainQuality {
+            view_radius: 2,
+            ..Default::default()
+        };
+        let mut terrain = TerrainRenderer::new(&gpu.device, gpu.surface_format, settings.clone(), quality).unwrap();
+        let camera = FlyCamera::new(1.0);
+        terrain.finish_streaming(&gpu.device, &gpu.queue, &camera);
+        assert_eq!(terrain.height_at(10.0, 10.0), None, "heights arrive with a later update");
+
+        // 25 chunks take two readbacks
+        for _ in 0..4 {
+            terrain.update(&gpu.device, &gpu.queue, &camera, 0.0);
+            gpu.device.poll(Maintain::Wait);
+        }
+        assert!(terrain.slots.iter().all(|slot| slot.heights.is_some()));
+
+        let sampler = TerrainSampler::new(&settings).with_quality(quality);
+        let tolerance = GPU_HEIGHT_TOLERANCE * settings.height_scale;
+        let spacing = quality.sample_spacing();
+        for (x, z) in [(0.0, 0.0), (3.0, 7.0), (-100.0, 150.0), (170.0, -120.0)] {
+            let (x, z) = (x * spacing, z * spacing);
+            let height = terrain.height_at(x, z).unwrap();
+            assert!((height - sampler.height_at(x, z)).abs() <= tolerance, "({}, {})", x, z);
+        }
+        // Between samples the height is interpolated
+        let (a, b) = (terrain.height_at(0.0, 0.0).unwrap(), terrain.height_at(spacing, 0.0).unwrap());
+        assert!((terrain.height_at(0.5 * spacing, 0.0).unwrap() - 0.5 * (a + b)).abs() < 1e-3);
+
+        let outside = 3.0 * quality.chunk_world_size;
+        assert_eq!(terrain.height_at(outside, 0.0), None);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn streaming_respects_budget_and_keeps_stale_chunks() {
+        use crate::camera::FlyCamera;
+        use crate::webgpu::test_gpu;
+
+        let gpu = test_gpu();
+        let quality = TerrainQuality {
+            view_radius: 4,
+            ..Default::default()
+        };
+        let mut terrain =
+            TerrainRenderer::new(&gpu.device, gpu.surface_format, TerrainSettings::default(), quality).unwrap();
+        terrain.set_chunk_budget(8);
+
+        // The initial fill streams in within the budget too
+        let camera = FlyCamera::new(1.0);
+        assert!(terrain.coord_to_slot.is_empty());
+        terrain.update(&gpu.device, &gpu.queue, &camera, 0.0);
+        assert_eq!(terrain.coord_to_slot.len(), 8);
+        terrain.finish_streaming(&gpu.device, &gpu.queue, &camera);
+
+        terrain.update_settings(TerrainSettings {
+            seed: 1,
+            ..Default::default()
+        });
+        terrain.check_regeneration();
+        terrain.update(&gpu.device, &gpu.queue, &camera, 0.0);
+
+        // Only the budget is regenerated, starting under the camera; the rest stay drawable
+        let fresh = terrain.slots.iter().filter(|slot| slot.settings_version == 1).count();
+        assert_eq!(fresh, 8);
+        assert_eq!(terrain.coord_to_slot.len(), quality.max_chunks());
+        assert!(terrain.slots.iter().all(|slot| slot.state == ChunkState::Ready));
+        let center = terrain.coord_to_slot[&ChunkCoord::new(0, 0)];
+        assert_eq!(terrain.slots[center].settings_version, 1);
+
+        terrain.finish_streaming(&gpu.device, &gpu.queue, &camera);
+        assert!(terrain.slots.iter().all(|slot| slot.settings_version == 1));
+    }
+
+    #[test]
+    fn appearance_changes_keep_geometry() {
+        let base = TerrainSettings::default();
+        let recolored = TerrainSettings {
+            color_grass: [0.1, 0.6, 0.2],
+            ambient: 0.5,
+            fog_start: 100.0,
+            ..base.clone()
+        };
+        assert_ne!(base, recolored);
+        assert!(base.same_geometry(&recolored));
+
+        let reseeded = TerrainSettings {
+            seed: 7,
+            ..base.clone()
+        };
+        assert!(!base.same_geometry(&reseeded));
+        let eroded = TerrainSettings {
+            erosion_iterations: 10,
+            ..base.clone()
+        };
+        assert!(!base.same_geometry(&eroded));
+    }
+
+    #[test]
+    fn base_color_bands_follow_sea_level() {
+        let lowered = TerrainSettings {
+            sea_level: -40.0,
+            ..Default::default()
+        };
+        let sand = Vec3::from(lowered.color_sand);
+        let shallow_water = Vec3::from(lowered.color_shallow_water);
+        // Just above the lowered sea level is beach, just below it is shallow water
+        assert!(lowered.base_color(-40.0, 0.0).abs_diff_eq(sand, 1e-6));
+        assert!(lowered.base_color(-40.01, 0.0).abs_diff_eq(shallow_water, 1e-2));
+        assert_eq!(lowered.base_color(60.0, 0.0), TerrainSettings::default().base_color(100.0, 0.0));
+
+        // With water on, terrain below sea level is seabed: sand darkening to rock
+        let flooded = TerrainSettings {
+            water_enabled: true,
+            ..lowered
+        };
+        assert!(flooded.base_color(-40.01, 0.0).abs_diff_eq(sand, 1e-3));
+        let dark_rock = Vec3::from(flooded.color_rock) * 0.7;
+        assert!(flooded.base_color(-200.0, 0.0).abs_diff_eq(dark_rock, 1e-6));
+    }
+
+    #[test]
+    fn quality_limits_are_validated() {
+        assert!(TerrainQuality::default().validate().is_ok());
+        for (chunk_size, view_radius) in [(4, 16), (512, 16), (64, 0), (64, 100), (256, 48)] {
+            let quality = TerrainQuality {
+                chunk_size,
+                view_radius,
+                ..Default::default()
+            };
+            assert!(quality.validate().is_err(), "{:?} should be rejected", quality);
         }
     }
 }
@@ -1,4 +1,5 @@
 /// Initialize panic hook and logging for WASM
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 pub fn init() {
     // Better panic messages in the console
     console_error_panic_hook::set_once();
@@ -0,0 +1,194 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::*;
+
+use crate::camera::CameraController;
+use crate::terrain::TerrainSettings;
+
+/// Water uniforms - must match WaterParams in water.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct WaterParams {
+    inverse_view_proj: [[f32; 4]; 4], // Depth buffer back to world positions
+    sea_level: f32,
+    extent: f32, // Half the side of the water sheet around the camera
+    time: f32,
+    wave_strength: f32,
+    wave_speed: f32,
+    clarity: f32,
+    foam_width: f32,
+    _padding: f32,
+}
+
+/// Water surface at sea level, drawn over the terrain. The terrain's depth is
+/// sampled rather than attached, both to hide the water behind the terrain and
+/// to find the water depth behind each pixel. Shares the terrain's camera
+/// (group 0) and color (group 2) bind groups.
+pub struct WaterRenderer {
+    pipeline: RenderPipeline,
+    params_buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    time: f32,
+}
+
+impl WaterRenderer {
+    pub fn new(
+        device: &Device,
+        surface_format: TextureFormat,
+        camera_layout: &BindGroupLayout,
+        color_layout: &BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Water Shader"),
+            source: ShaderSource::Wgsl(include_str!("../shaders/water.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Water Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    // Read as plain floats: GLSL backends can't load from depth textures
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Water Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &bind_group_layout, color_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Water Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format: surface_format,
+                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: ColorWrites::COLOR,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                cull_mode: None, // Also seen from below
+                ..Default::default()
+            },
+            // Depth tested in the fragment shader against the sampled terrain depth
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Water Params Buffer"),
+            size: std::mem::size_of::<WaterParams>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            params_buffer,
+            bind_group_layout,
+            time: 0.0,
+        }
+    }
+
+    /// Advance the wave animation
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    /// Write this frame's uniforms; `extent` is half the side of the water sheet
+    pub fn write_params(&self, queue: &Queue, camera: &dyn CameraController, settings: &TerrainSettings, extent: f32) {
+        let params = WaterParams {
+            inverse_view_proj: camera.view_projection_matrix().inverse().to_cols_array_2d(),
+            sea_level: settings.sea_level,
+            extent,
+            time: self.time,
+            wave_strength: settings.wave_strength.max(0.0),
+            wave_speed: settings.wave_speed,
+            clarity: settings.water_clarity.max(0.01),
+            foam_width: settings.foam_width.max(0.0),
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+
+    /// Draw the water over the terrain already in `color_view` and `depth_view`
+    pub fn render(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        color_view: &TextureView,
+        depth_view: &TextureView,
+        camera_bind_group: &BindGroup,
+        color_bind_group: &BindGroup,
+    ) {
+        // The depth view is replaced on resize, so bind it per frame
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Water Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(depth_view),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Water Render Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: color_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            // Not attached: read-only depth attachments aren't available everywhere (GL),
+            // and the depth is bound for sampling
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &bind_group, &[]);
+        render_pass.set_bind_group(2, color_bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
@@ -1,10 +1,36 @@
 use wgpu::*;
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 use web_sys::HtmlCanvasElement;
 
+/// Requested when the adapter has them; terrain then draws every chunk in one call
+const OPTIONAL_FEATURES: Features = Features::MULTI_DRAW_INDIRECT.union(Features::INDIRECT_FIRST_INSTANCE);
+
+/// Where rendered frames end up
+pub enum RenderTarget {
+    /// Browser canvas surface
+    Surface(Surface<'static>),
+    /// Offscreen color texture that can be copied back to the CPU
+    Offscreen(Texture),
+}
+
+/// Color target for a single frame, presented after submission
+pub struct Frame {
+    pub view: TextureView,
+    surface_texture: Option<SurfaceTexture>,
+}
+
+impl Frame {
+    pub fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
+
 pub struct GpuState {
     pub device: Device,
     pub queue: Queue,
-    pub surface: Surface<'static>,
+    pub target: RenderTarget,
     pub config: SurfaceConfiguration,
     pub surface_format: TextureFormat,
     pub depth_texture: Texture,
@@ -12,6 +38,7 @@ pub struct GpuState {
 }
 
 impl GpuState {
+    #[cfg(all(feature = "web", target_arch = "wasm32"))]
     pub async fn new(canvas: &HtmlCanvasElement) -> Result<Self, String> {
         // Create instance with WebGPU backend
         let instance = Instance::new(InstanceDescriptor {
@@ -41,7 +68,7 @@ impl GpuState {
             .request_device(
                 &DeviceDescriptor {
                     label: Some("Main Device"),
-                    required_features: Features::empty(),
+                    required_features: adapter.features() & OPTIONAL_FEATURES,
                     required_limits: Limits::downlevel_webgl2_defaults()
                         .using_resolution(adapter.limits()),
                     memory_hints: Default::default(),
@@ -88,7 +115,82 @@ impl GpuState {
         Ok(Self {
             device,
             queue,
-            surface,
+            target: RenderTarget::Surface(surface),
+            config,
+            surface_format,
+            depth_texture,
+            depth_view,
+        })
+    }
+
+    /// Create a device without a window, rendering into an offscreen texture.
+    /// Falls back to software adapters (lavapipe, llvmpipe) when no GPU is present.
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self, String> {
+        let instance = Instance::new(InstanceDescriptor {
+            backends: Backends::PRIMARY | Backends::GL,
+            ..Default::default()
+        });
+
+        let mut adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await;
+        if adapter.is_none() {
+            adapter = instance
+                .request_adapter(&RequestAdapterOptions {
+                    power_preference: PowerPreference::HighPerformance,
+                    compatible_surface: None,
+                    force_fallback_adapter: true,
+                })
+                .await;
+        }
+        let adapter = adapter.ok_or("No suitable GPU adapter found (hardware or software)")?;
+
+        log::info!("Using adapter: {:?}", adapter.get_info().name);
+
+        // Native adapters report real limits; the WebGL2 baseline has no storage buffers
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("Headless Device"),
+                    required_features: adapter.features() & OPTIONAL_FEATURES,
+                    required_limits: adapter.limits(),
+                    memory_hints: Default::default(),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to create device: {:?}", e))?;
+
+        let surface_format = TextureFormat::Rgba8UnormSrgb;
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format: surface_format,
+            width,
+            height,
+            present_mode: PresentMode::AutoVsync,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        let color_texture = Self::create_offscreen_texture(&device, &config);
+        let (depth_texture, depth_view) = Self::create_depth_texture(&device, width, height);
+
+        log::info!(
+            "Headless GPU initialized: {}x{}, format: {:?}",
+            width,
+            height,
+            surface_format
+        );
+
+        Ok(Self {
+            device,
+            queue,
+            target: RenderTarget::Offscreen(color_texture),
             config,
             surface_format,
             depth_texture,
@@ -96,10 +198,60 @@ impl GpuState {
         })
     }
 
+    /// Get the color target for the next frame.
+    /// Returns None when the frame should be skipped (e.g. the surface was lost).
+    pub fn acquire_frame(&mut self) -> Option<Frame> {
+        let surface = match &self.target {
+            RenderTarget::Surface(surface) => surface,
+            RenderTarget::Offscreen(texture) => {
+                return Some(Frame {
+                    view: texture.create_view(&TextureViewDescriptor::default()),
+                    surface_texture: None,
+                });
+            }
+        };
+
+        let output = match surface.get_current_texture() {
+            Ok(output) => output,
+            Err(e) => match e {
+                SurfaceError::Lost | SurfaceError::Outdated => {
+                    self.resize(self.config.width, self.config.height);
+                    return None;
+                }
+                SurfaceError::Timeout => {
+                    log::warn!("Surface timeout, skipping frame");
+                    return None;
+                }
+                SurfaceError::OutOfMemory => {
+                    log::error!("Surface out of memory, stopping render loop");
+                    return None;
+                }
+            },
+        };
+
+        Some(Frame {
+            view: output.texture.create_view(&TextureViewDescriptor::default()),
+            surface_texture: Some(output),
+        })
+    }
+
+    /// Offscreen color texture, if this state was created headless
+    pub fn offscreen_texture(&self) -> Option<&Texture> {
+        match &self.target {
+            RenderTarget::Offscreen(texture) => Some(texture),
+            RenderTarget::Surface(_) => None,
+        }
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
         self.config.width = width;
         self.config.height = height;
-        self.surface.configure(&self.device, &self.config);
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Offscreen(texture) => {
+                *texture = Self::create_offscreen_texture(&self.device, &self.config);
+            }
+        }
 
         let (depth_texture, depth_view) = Self::create_depth_texture(&self.device, width, height);
         self.depth_texture = depth_texture;
@@ -108,6 +260,23 @@ impl GpuState {
         log::info!("Resized to {}x{}", width, height);
     }
 
+    fn create_offscreen_texture(device: &Device, config: &SurfaceConfiguration) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("Offscreen Color Texture"),
+            size: Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
+        })
+    }
+
     fn create_depth_texture(device: &Device, width: u32, height: u32) -> (Texture, TextureView) {
         let texture = device.create_texture(&TextureDescriptor {
             label: Some("Depth Texture"),
@@ -129,3 +298,11 @@ impl GpuState {
 
     pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
 }
+
+/// Headless device for tests that need a GPU. These tests are `#[ignore]`d so a
+/// machine without an adapter reports them as skipped; run them with
+/// `cargo test -- --include-ignored`.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub(crate) fn test_gpu() -> GpuState {
+    pollster::block_on(GpuState::new_headless(1, 1)).expect("GPU tests need a hardware or software adapter")
+}
@@ -1,4 +1,4 @@
-import type { ColorTheme, SkySettings, ParticleSettings, SliderConfig, SliderSetup, TerrainSettings } from './types';
+import type { ColorTheme, SkySettings, ParticleSettings, SliderConfig, SliderSetup, TerrainQuality, TerrainSettings } from './types';
 
 // Comparison tolerances for color and number equality checks
 export const COLOR_EPSILON = 0.005;
@@ -261,6 +261,16 @@ export const WEATHER_PRESETS: Record<string, Partial<ParticleSettings>> = {
     },
 };
 
+// Chunk resolution and view radius levels for the quality selector. All chunk
+// heights share one storage binding (128 MiB in WebGPU's default limits), so ultra
+// raises the resolution to 97 rather than 128 to keep the full view radius.
+export const QUALITY_PRESETS: Record<string, TerrainQuality> = {
+    'low': { chunk_size: 33, chunk_world_size: 256, view_radius: 10 },
+    'medium': { chunk_size: 64, chunk_world_size: 256, view_radius: 16 },
+    'high': { chunk_size: 64, chunk_world_size: 256, view_radius: 24 },
+    'ultra': { chunk_size: 97, chunk_world_size: 256, view_radius: 24 },
+};
+
 // Generation settings that require terrain regeneration
 export const GENERATION_SETTINGS: (keyof TerrainSettings)[] = [
     'pattern_type',
@@ -270,6 +280,14 @@ export const GENERATION_SETTINGS: (keyof TerrainSettings)[] = [
     'octaves',
     'warp_strength',
     'roughness',
+    'erosion_iterations',
+    'erosion_rain',
+    'erosion_sediment_capacity',
+    'erosion_evaporation',
+    'thermal_iterations',
+    'thermal_talus_angle',
+    'thermal_strength',
+    'ao_radius',
 ];
 
 // Color settings that only require update (no regeneration)
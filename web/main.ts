@@ -1,4 +1,4 @@
-import init, { update_terrain_settings, get_terrain_settings, regenerate_terrain, update_sky_settings, get_sky_settings, update_particle_settings, get_particle_settings, get_default_terrain_settings, get_default_sky_settings, get_default_particle_settings, get_preset_list, get_preset, get_default_preset_id } from '../pkg/procedural_terrain_simulator.js';
+import init, { update_terrain_settings, get_terrain_settings, regenerate_terrain, update_sky_settings, get_sky_settings, update_particle_settings, get_particle_settings, get_default_terrain_settings, get_default_sky_settings, get_default_particle_settings, get_preset_list, get_preset, get_default_preset_id, set_terrain_quality } from '../pkg/procedural_terrain_simulator.js';
 
 // Import types
 import type { TerrainSettings, SkySettings, ParticleSettings, PresetInfo, FullPreset } from './types';
@@ -8,6 +8,7 @@ import {
     COLOR_THEMES,
     SKY_PRESETS,
     WEATHER_PRESETS,
+    QUALITY_PRESETS,
     SLIDER_SETUP,
     SNOW_WEATHER_TYPES,
 } from './constants';
@@ -189,9 +190,28 @@ function collectTerrainSettings(seed?: number): TerrainSettings {
         roughness: sliderToValue('roughness', parseFloat(getInput('roughness').value)),
         pattern_type: parseInt(getSelect('pattern-type').value),
         seed: seed ?? generateSeed(),
+        erosion_iterations: base.erosion_iterations,
+        erosion_rain: base.erosion_rain,
+        erosion_sediment_capacity: base.erosion_sediment_capacity,
+        erosion_evaporation: base.erosion_evaporation,
+        thermal_iterations: base.thermal_iterations,
+        thermal_talus_angle: base.thermal_talus_angle,
+        thermal_strength: base.thermal_strength,
         ambient: base.ambient,
         fog_start: base.fog_start,
         fog_distance: base.fog_distance,
+        sun_direction: base.sun_direction,
+        sun_color: base.sun_color,
+        sun_intensity: base.sun_intensity,
+        sun_from_sky: base.sun_from_sky,
+        ao_strength: base.ao_strength,
+        ao_radius: base.ao_radius,
+        water_enabled: base.water_enabled,
+        sea_level: base.sea_level,
+        wave_strength: base.wave_strength,
+        wave_speed: base.wave_speed,
+        water_clarity: base.water_clarity,
+        foam_width: base.foam_width,
         color_abyss: hexToRgb(getInput('color-abyss').value),
         color_deep_water: hexToRgb(getInput('color-deep-water').value),
         color_shallow_water: hexToRgb(getInput('color-shallow-water').value),
@@ -517,6 +537,9 @@ function setupSettingsPanel() {
     const sunColor = document.getElementById('sun-color') as HTMLInputElement;
     const moonColor = document.getElementById('moon-color') as HTMLInputElement;
 
+    // Quality controls
+    const terrainQualitySelect = document.getElementById('terrain-quality') as HTMLSelectElement;
+
     // Weather/particle controls
     const weatherPresetSelect = document.getElementById('weather-preset') as HTMLSelectElement;
     const particleDensitySlider = document.getElementById('particle-density') as HTMLInputElement;
@@ -637,6 +660,18 @@ function setupSettingsPanel() {
         }
     });
 
+    // Apply quality level (rebuilds the chunk pool immediately)
+    terrainQualitySelect.addEventListener('change', () => {
+        const quality = QUALITY_PRESETS[terrainQualitySelect.value];
+        if (quality) {
+            try {
+                set_terrain_quality(quality);
+            } catch (err) {
+                console.error('Failed to change terrain quality:', err);
+            }
+        }
+    });
+
     // Apply settings with smart change detection
     applyButton.addEventListener('click', () => {
         try {
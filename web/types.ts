@@ -8,9 +8,28 @@ export interface TerrainSettings {
     roughness: number;
     pattern_type: number;
     seed: number;
+    erosion_iterations: number; // Clamped to 32
+    erosion_rain: number;
+    erosion_sediment_capacity: number;
+    erosion_evaporation: number;
+    thermal_iterations: number; // Clamped to 32
+    thermal_talus_angle: number;
+    thermal_strength: number;
     ambient: number;
     fog_start: number;
     fog_distance: number;
+    sun_direction: [number, number, number]; // Used when sun_from_sky is off or there are no suns
+    sun_color: [number, number, number];
+    sun_intensity: number;
+    sun_from_sky: boolean; // Light from the highest sky sun
+    ao_strength: number; // 0 disables the darkening
+    ao_radius: number; // World units searched for occluders; 0 skips the bake
+    water_enabled: boolean; // When off, terrain below sea level keeps the water color bands
+    sea_level: number;
+    wave_strength: number; // Steepest slope of the wave normals
+    wave_speed: number;
+    water_clarity: number; // World units light travels through water before fading to 1/e
+    foam_width: number; // Water depth below which shoreline foam appears
     color_abyss: [number, number, number];
     color_deep_water: [number, number, number];
     color_shallow_water: [number, number, number];
@@ -23,6 +42,38 @@ export interface TerrainSettings {
     color_sky_horizon: [number, number, number];
 }
 
+// Chunk resolution and view distance; changing these rebuilds the chunk pool
+export interface TerrainQuality {
+    chunk_size: number;
+    chunk_world_size: number;
+    view_radius: number;
+}
+
+// Fly camera motion
+export interface CameraSettings {
+    move_speed: number;
+    look_sensitivity: number;
+    acceleration: number;         // Multiples of full speed gained per second; 0 = instant
+    damping: number;              // Speed decay per second after release; 0 = instant stop
+    sprint_multiplier: number;    // While F is held
+    precision_multiplier: number; // While X is held
+    scroll_action: 'zoom' | 'speed';
+}
+
+export interface ShadowSettings {
+    enabled: boolean;
+    resolution: number;    // Texels per side of each cascade's shadow map (256-4096)
+    cascade_count: number; // 1-4
+    max_distance: number;  // View depth covered by the last cascade
+}
+
+export interface DayCycleSettings {
+    enabled: boolean;     // When off, the sky and terrain settings are used as they are
+    time_of_day: number;  // Hours, 0-24 (12 is noon)
+    time_scale: number;   // Game hours per real second
+    paused: boolean;
+}
+
 // Color theme for terrain and sky
 export interface ColorTheme {
     abyss: [number, number, number];